@@ -0,0 +1,10 @@
+in vec3 normal;
+
+out vec4 frag;
+
+const vec3 albedo = vec3(0.45, 0.3, 0.2);
+
+void main() {
+  float lambert = max(dot(normalize(normal), normalize(sun_direction)), 0.0);
+  frag = vec4(albedo * (ambient() + sun_radiance * lambert), 1.0);
+}
@@ -0,0 +1,27 @@
+out vec3 normal;
+
+uniform mat4 view_projection;
+uniform mat4 model;
+uniform vec3 half_extents;
+
+const vec3 NORMALS[6] = vec3[](
+  vec3(1.0, 0.0, 0.0), vec3(-1.0, 0.0, 0.0),
+  vec3(0.0, 1.0, 0.0), vec3(0.0, -1.0, 0.0),
+  vec3(0.0, 0.0, 1.0), vec3(0.0, 0.0, -1.0)
+);
+// Two triangles covering a face, in the face's own axes
+const vec2 CORNERS[6] = vec2[](
+  vec2(-1.0, -1.0), vec2(1.0, -1.0), vec2(-1.0, 1.0),
+  vec2(-1.0, 1.0), vec2(1.0, -1.0), vec2(1.0, 1.0)
+);
+
+void main() {
+  vec3 n = NORMALS[gl_VertexID / 6];
+  vec2 corner = CORNERS[gl_VertexID % 6];
+  vec3 u = n.yzx;
+  vec3 v = cross(n, u);
+  vec3 local = (n + corner.x * u + corner.y * v) * half_extents;
+
+  normal = mat3(model) * n;
+  gl_Position = view_projection * model * vec4(local, 1.0);
+}
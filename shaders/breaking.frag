@@ -0,0 +1,44 @@
+in vec2 uv;
+
+uniform sampler2D heightmap;
+uniform sampler2D velocity; // a: vertical acceleration
+uniform sampler2D jacobian; // r: dDx/dx, g: dDz/dz, b: dDx/dz
+uniform int cell_size;
+uniform float choppiness;
+uniform float jacobian_threshold;
+uniform float acceleration_threshold; // as a fraction of g
+
+out vec4 frag;
+
+const float g = 9.81;
+
+void main() {
+  ivec2 cell = ivec2(gl_FragCoord.xy);
+
+  // r: intensity, g: world x, b: world z, a: height
+  frag = vec4(0.0);
+  for (int y = 0; y < cell_size; y++) {
+    for (int x = 0; x < cell_size; x++) {
+      ivec2 texel = cell * cell_size + ivec2(x, y);
+      vec3 derivatives = texelFetch(jacobian, texel, 0).rgb;
+      float acceleration = texelFetch(velocity, texel, 0).a;
+
+      // Choppy waves pinch towards crests, where the height is positive
+      vec3 d = derivatives * choppiness;
+      float determinant = (1.0 - d.x) * (1.0 - d.y) - d.z * d.z;
+
+      float folding = jacobian_threshold - determinant;
+      float falling = -acceleration / g - acceleration_threshold;
+      if (folding <= 0.0 || falling <= 0.0) {
+        continue;
+      }
+
+      float intensity = folding + falling;
+      if (intensity > frag.r) {
+        float height = texelFetch(heightmap, texel, 0).r;
+        // Texel centers sit half a unit off the vertex grid
+        frag = vec4(intensity, vec2(texel) + 0.5, height);
+      }
+    }
+  }
+}
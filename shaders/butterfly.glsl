@@ -0,0 +1,52 @@
+in vec2 uv;
+
+uniform sampler2D twiddle_indices;
+
+uniform int stage;
+uniform int direction;
+uniform int forward;
+uniform int invert;
+uniform int n;
+
+// Complex multiplication
+vec2 cmul(vec2 c0, vec2 c1) {
+  vec2 c;
+  c.x = c0.x * c1.x - c0.y * c1.y;
+  c.y = c0.x * c1.y + c0.y * c1.x;
+  return c;
+}
+
+vec4 get_pixel(sampler2D sampler, ivec2 uv) {
+  vec2 size = textureSize(sampler, 0);
+  return texture(sampler, (uv + 0.5) / size);
+}
+
+// Each texel holds two complex numbers, in rg and ba
+vec4 get_input_pixel(sampler2D input_texture, ivec2 uv) {
+  if (direction != 0) uv = uv.yx; // Flip coordinates
+  return get_pixel(input_texture, uv);
+}
+
+// One butterfly of the current stage, for the texel being shaded
+vec4 butterfly(sampler2D input_texture) {
+  vec2 frag_coord = gl_FragCoord.xy - 0.5;
+  if (direction != 0) frag_coord = frag_coord.yx; // Flip coordinates
+
+  vec4 twiddle = get_pixel(twiddle_indices, ivec2(stage, frag_coord.x)).rgba;
+  vec2 w = vec2(twiddle.x, twiddle.y);
+  if (forward != 0) w.y = -w.y; // Conjugate twiddles give the forward DFT
+  vec4 p = get_input_pixel(input_texture, ivec2(twiddle.z, frag_coord.y));
+  vec4 q = get_input_pixel(input_texture, ivec2(twiddle.w, frag_coord.y));
+
+  //Butterfly operation
+  vec4 H = vec4(p.xy + cmul(w, q.xy), p.zw + cmul(w, q.zw));
+
+  if (invert != 0) {
+    // Last stage of an inverse transform: apply the (-1)^(x+y) sign and the
+    // 1/N² scaling here instead of in a separate pass
+    vec2 xy = gl_FragCoord.xy - 0.5;
+    float perm = mod(dot(xy, xy), 2) * -2.0 + 1.0;
+    return perm * H / float(n * n);
+  }
+  return H;
+}
@@ -0,0 +1,10 @@
+in vec2 source;
+
+out vec4 frag;
+
+void main() {
+  // Texels of the surface whose light lands on this one: one for calm
+  // water, more where the waves focus it
+  float area = abs(determinant(mat2(dFdx(source), dFdy(source))));
+  frag = vec4(area, 0.0, 0.0, 0.0);
+}
@@ -0,0 +1,77 @@
+out vec2 source; // where the light crossed the surface, in texels
+
+uniform sampler2D heightmap;
+uniform vec3 sun_direction;
+uniform float refractive_index;
+uniform float depth;
+// Set to draw the strips around the tile rather than the tile itself
+uniform int edges;
+
+// Two triangles per cell of the grid of texels
+const ivec2 CORNERS[6] = ivec2[](
+  ivec2(0, 0), ivec2(1, 0), ivec2(0, 1),
+  ivec2(1, 0), ivec2(1, 1), ivec2(0, 1)
+);
+// How far light is followed sideways past the edges of the texture, in
+// texels. Keep in sync with caustics.rs
+const int MARGIN = 16;
+
+float height_at(vec2 position, float side) {
+  return texture(heightmap, mod(position / side, 1.0)).r;
+}
+
+// Sunlight after it has refracted through a surface facing `normal`, with
+// the sun kept above the horizon
+vec3 refracted(vec3 normal) {
+  vec3 sun = normalize(vec3(sun_direction.x, max(sun_direction.y, 0.1),
+    sun_direction.z));
+  return refract(-sun, normal, 1.0 / refractive_index);
+}
+
+// Cell of the endlessly repeating grid drawn by `index`. Without `edges`,
+// the cells of the tile; with them, one of four strips MARGIN cells wide
+// that wind around the tile, for light crossing in from its neighbours
+ivec2 grid_cell(int index, int grid) {
+  if (edges == 0) {
+    return ivec2(index % grid, index / grid);
+  }
+  int strip = grid + MARGIN;
+  int along = index % strip;
+  int across = index / strip;
+  switch (gl_InstanceID) {
+    case 0: return ivec2(along, across - MARGIN);
+    case 1: return ivec2(grid + across, along);
+    case 2: return ivec2(along - MARGIN, grid + across);
+    default: return ivec2(across - MARGIN, along - MARGIN);
+  }
+}
+
+void main() {
+  float side = float(textureSize(heightmap, 0).x);
+  int grid = int(side);
+  ivec2 shifted = grid_cell(gl_VertexID / 6, grid);
+  ivec2 cell = (shifted + grid) % grid;
+  vec2 position = vec2(cell + CORNERS[gl_VertexID % 6]);
+  source = position;
+
+  // Light from cells of the neighbouring tiles lands shifted by a tile, so
+  // light that leaves one edge comes back in at the other and the texture
+  // tiles
+  vec2 tile = vec2(shifted - cell);
+
+  vec3 normal = normalize(vec3(
+    height_at(position - vec2(1.0, 0.0), side)
+      - height_at(position + vec2(1.0, 0.0), side),
+    2.0,
+    height_at(position - vec2(0.0, 1.0), side)
+      - height_at(position + vec2(0.0, 1.0), side)
+  ));
+  vec3 ray = refracted(normal);
+  vec3 calm = refracted(vec3(0.0, 1.0, 0.0));
+
+  // Where the light lands, relative to where calm water would send it, so
+  // the texture lines up with the surface straight up toward the sun
+  vec2 landing = position + ray.xz * depth / max(-ray.y, 0.01)
+    - calm.xz * depth / -calm.y;
+  gl_Position = vec4((landing + tile) / side * 2.0 - 1.0, 0.0, 1.0);
+}
@@ -4,10 +4,12 @@ in vec2 uv;
 
 uniform sampler2D input_texture;
 
-out vec4 frag;
+layout (location = 0) out vec4 frag;
+layout (location = 1) out vec4 velocity;
+layout (location = 2) out vec4 jacobian;
 
 uniform int n = 512;
-uniform int scale = 1000;
+uniform int scale;
 uniform float time;
 
 const float g = 9.81;
@@ -20,15 +22,26 @@ vec2 cmul(vec2 c0, vec2 c1) {
   return c;
 }
 
-void main(void) {
-  vec2 xy = gl_FragCoord.xy - 0.5 - float(n) / 2.0;
+vec2 conj(vec2 c) {
+  return vec2(c.x, -c.y);
+}
+
+struct Wave {
+  vec2 k;
+  float w;
+  vec2 forward;  // h0(k) e^(iwt)
+  vec2 backward; // conj(h0(-k)) e^(-iwt)
+};
+
+Wave wave_at(ivec2 texel) {
+  vec2 xy = vec2(texel) - float(n) / 2.0;
   vec2 k = TAU * xy / scale;
 
   float magnitude = max(length(k), 0.00001);
 
   float w = sqrt(g * magnitude);
 
-  vec4 h0k = texture(input_texture, uv);
+  vec4 h0k = texelFetch(input_texture, texel, 0);
   vec2 fou_amp = h0k.rg;
   vec2 fou_amp_conj = vec2(h0k.b, -h0k.a);
 
@@ -39,17 +52,86 @@ void main(void) {
   vec2 exp_iwt = vec2(cosinus, sinus);
   vec2 exp_iwt_inv = vec2(cosinus, -sinus);
 
+  return Wave(k, w, cmul(fou_amp, exp_iwt), cmul(fou_amp_conj, exp_iwt_inv));
+}
+
+// Spectra of the water velocity at the surface. The forward term travels
+// against k and the backward term along it, so they move the water in
+// opposite directions. Horizontal velocity is scaled from the spectrum's
+// patch size to the N world units it is drawn across.
+void velocity_at(ivec2 texel, out vec2 vx, out vec2 vz, out vec2 vy) {
+  Wave wave = wave_at(texel);
+  vec2 difference = wave.forward - wave.backward;
+  vec2 direction = wave.k / max(length(wave.k), 0.00001);
+  float to_world = float(n) / float(scale);
+
+  vx = -wave.w * direction.x * difference * to_world;
+  vz = -wave.w * direction.y * difference * to_world;
+  vy = cmul(vec2(0.0, wave.w), difference);
+}
+
+// Spectra of the derivatives of the horizontal displacement choppy waves
+// would have, which breaking detection builds the Jacobian from, and of the
+// vertical acceleration. All of them are the height spectrum times an even
+// function of k, and stay the same when the spectrum's patch is scaled to
+// world units.
+void breaking_at(
+  ivec2 texel,
+  out vec2 dxdx,
+  out vec2 dzdz,
+  out vec2 dxdz,
+  out vec2 ay
+) {
+  Wave wave = wave_at(texel);
+  vec2 height = wave.forward + wave.backward;
+  float magnitude = max(length(wave.k), 0.00001);
+
+  dxdx = wave.k.x * wave.k.x / magnitude * height;
+  dzdz = wave.k.y * wave.k.y / magnitude * height;
+  dxdz = wave.k.x * wave.k.y / magnitude * height;
+  ay = -wave.w * wave.w * height;
+}
+
+void main(void) {
+  ivec2 texel = ivec2(gl_FragCoord.xy);
+  Wave wave = wave_at(texel);
+  float magnitude = max(length(wave.k), 0.00001);
+
   // dy
-  vec2 h_k_t_dy = cmul(fou_amp, exp_iwt) + cmul(fou_amp_conj, exp_iwt_inv);
+  vec2 h_k_t_dy = wave.forward + wave.backward;
 
   // dx
-  vec2 dx = vec2(0.0, -k.x / magnitude);
+  vec2 dx = vec2(0.0, -wave.k.x / magnitude);
   vec2 h_k_t_dx = cmul(dx, h_k_t_dy);
 
   // dz
-  vec2 dy = vec2(0.0, -k.y / magnitude);
+  vec2 dy = vec2(0.0, -wave.k.y / magnitude);
   vec2 h_k_t_dz = cmul(dy, h_k_t_dy);
 
   frag.xy = h_k_t_dy;
-  frag.zw = vec2(0.0, 1.0);
+  frag.zw = vec2(0.0);
+
+  // Two real fields can share one transform as the real and imaginary parts
+  // of a complex one, as long as both spectra are hermitian. Taking the
+  // hermitian part of each keeps exactly the real part the heights use.
+  ivec2 mirror = (n - texel) % n;
+  vec2 vx, vz, vy, mirror_vx, mirror_vz, mirror_vy;
+  velocity_at(texel, vx, vz, vy);
+  velocity_at(mirror, mirror_vx, mirror_vz, mirror_vy);
+  vx = (vx + conj(mirror_vx)) / 2.0;
+  vz = (vz + conj(mirror_vz)) / 2.0;
+  vy = (vy + conj(mirror_vy)) / 2.0;
+
+  vec2 dxdx, dzdz, dxdz, ay, mirror_dxdx, mirror_dzdz, mirror_dxdz, mirror_ay;
+  breaking_at(texel, dxdx, dzdz, dxdz, ay);
+  breaking_at(mirror, mirror_dxdx, mirror_dzdz, mirror_dxdz, mirror_ay);
+  dxdx = (dxdx + conj(mirror_dxdx)) / 2.0;
+  dzdz = (dzdz + conj(mirror_dzdz)) / 2.0;
+  dxdz = (dxdz + conj(mirror_dxdz)) / 2.0;
+  ay = (ay + conj(mirror_ay)) / 2.0;
+
+  velocity.xy = vx + cmul(vec2(0.0, 1.0), vz);
+  velocity.zw = vy + cmul(vec2(0.0, 1.0), ay);
+  jacobian.xy = dxdx + cmul(vec2(0.0, 1.0), dzdz);
+  jacobian.zw = dxdz;
 }
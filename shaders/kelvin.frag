@@ -0,0 +1,65 @@
+flat in int vessel;
+in vec2 position;
+
+uniform sampler2D paths;
+
+out vec4 frag;
+
+const float G = 9.81;
+const float TAN_KELVIN = 0.35355339; // tan(19.47°), or 1 / sqrt(8)
+
+// Phase of the wave component whose crests meet the track at atan(t), seen
+// `behind` the ship along its track and `side` off it. Stationary where
+// 2 side t² - behind t + side = 0.
+float phase(float k0, float behind, float side, float t) {
+  return k0 * (behind - side * t) * sqrt(1.0 + t * t);
+}
+
+void main() {
+  vec4 header = texelFetch(paths, ivec2(0, vessel), 0);
+  float speed = header.x;
+  float amplitude = header.y;
+  int count = int(header.z);
+
+  // Find how far behind the ship along its path this point is, and how far
+  // to the side of the path
+  float behind = 0.0;
+  float side = 1e20;
+  for (int i = 1; i < count; i++) {
+    vec4 a = texelFetch(paths, ivec2(i, vessel), 0);
+    vec4 b = texelFetch(paths, ivec2(i + 1, vessel), 0);
+    vec2 segment = b.xy - a.xy;
+    float t = dot(position - a.xy, segment) / max(dot(segment, segment), 1e-6);
+    t = clamp(t, 0.0, 1.0);
+    float distance = length(position - a.xy - segment * t);
+    if (distance < side) {
+      side = distance;
+      behind = mix(a.z, b.z, t);
+    }
+  }
+  float wake_length = texelFetch(paths, ivec2(count, vessel), 0).z;
+
+  float tan_a = side / max(behind, 1e-4);
+  if (behind <= 0.0 || tan_a >= TAN_KELVIN) {
+    frag = vec4(0.0);
+    return;
+  }
+
+  // Stationary phase: two wave components reach every point inside the
+  // wedge, one transverse and one divergent, merging at its edge
+  float disc = sqrt(1.0 - 8.0 * tan_a * tan_a);
+  float transverse = 2.0 * tan_a / (1.0 + disc);
+  float divergent = (1.0 + disc) / (4.0 * max(tan_a, 1e-4));
+
+  float k0 = G / max(speed * speed, 0.01);
+  float r = length(vec2(behind, side));
+  float height = cos(phase(k0, behind, side, transverse))
+    // Damp the short divergent waves near the track, where they would alias
+    + cos(phase(k0, behind, side, divergent)) / (1.0 + divergent * divergent);
+  height *= amplitude / sqrt(1.0 + k0 * r) / sqrt(max(disc, 0.2));
+
+  height *= smoothstep(1.0, 0.9, tan_a / TAN_KELVIN);
+  height *= 1.0 - smoothstep(0.7, 1.0, behind / wake_length);
+
+  frag = vec4(height, 0.0, 0.0, 0.0);
+}
@@ -0,0 +1,27 @@
+flat out int vessel;
+out vec2 position; // world x and z
+
+uniform sampler2D paths;
+uniform vec2 origin;
+uniform float size;
+
+void main() {
+  vessel = gl_InstanceID;
+  int count = int(texelFetch(paths, ivec2(0, vessel), 0).z);
+
+  vec2 low = vec2(1e20);
+  vec2 high = vec2(-1e20);
+  float wake_length = 0.0;
+  for (int i = 1; i <= count; i++) {
+    vec4 point = texelFetch(paths, ivec2(i, vessel), 0);
+    low = min(low, point.xy);
+    high = max(high, point.xy);
+    wake_length = point.z;
+  }
+
+  // The wake spreads at most tan(19.47°) of its length to either side
+  float margin = wake_length * 0.36 + 1.0;
+  vec2 corner = vec2(gl_VertexID % 2, gl_VertexID / 2);
+  position = mix(low - margin, high + margin, corner);
+  gl_Position = vec4((position - origin) / size * 2.0 - 1.0, 0.0, 1.0);
+}
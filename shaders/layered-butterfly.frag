@@ -0,0 +1,14 @@
+// The same butterfly as butterfly.frag, applied to three layers at once
+uniform sampler2D layer0;
+uniform sampler2D layer1;
+uniform sampler2D layer2;
+
+layout (location = 0) out vec4 frag0;
+layout (location = 1) out vec4 frag1;
+layout (location = 2) out vec4 frag2;
+
+void main() {
+  frag0 = butterfly(layer0);
+  frag1 = butterfly(layer1);
+  frag2 = butterfly(layer2);
+}
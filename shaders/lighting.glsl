@@ -0,0 +1,38 @@
+// Set from `Lighting`, see lighting.rs
+uniform vec3 sun_direction;
+uniform vec3 sun_radiance;
+uniform vec3 sky_color;
+uniform vec3 horizon_color;
+uniform float fog_density;
+uniform float fog_falloff;
+uniform float fog_sun_glow;
+
+vec3 sky(vec3 direction) {
+  return mix(horizon_color, sky_color, sqrt(max(direction.y, 0.0)));
+}
+
+// Light from the whole sky onto a surface facing straight up
+vec3 ambient() {
+  return (sky_color + horizon_color) / 2.0;
+}
+
+// Fraction of the light from `position` that height fog scatters away before
+// it reaches `eye`: the fog thins out exponentially with height, and its
+// density is integrated along the line of sight
+float fog(vec3 eye, vec3 position) {
+  vec3 ray = position - eye;
+  float reach = length(ray);
+  float rise = ray.y * fog_falloff;
+  float thickness = fog_density * exp(-eye.y * fog_falloff) * reach;
+  if (abs(rise) > 0.0001) {
+    thickness *= (1.0 - exp(-rise)) / rise;
+  }
+  return 1.0 - exp(-thickness);
+}
+
+// Fog lit by `horizon`, the sky straight ahead at eye level, plus sunlight
+// scattered forward when looking along `view` toward the sun
+vec3 fog_color(vec3 horizon, vec3 view) {
+  float toward_sun = max(dot(view, normalize(sun_direction)), 0.0);
+  return horizon + sun_radiance * fog_sun_glow * pow(toward_sun, 8.0);
+}
@@ -0,0 +1,23 @@
+in vec2 uv;
+
+uniform sampler2D lhs;
+uniform sampler2D rhs;
+uniform int n;
+
+out vec4 frag;
+
+// Complex multiplication
+vec2 cmul(vec2 c0, vec2 c1) {
+  vec2 c;
+  c.x = c0.x * c1.x - c0.y * c1.y;
+  c.y = c0.x * c1.y + c0.y * c1.x;
+  return c;
+}
+
+void main() {
+  vec2 a = texture(lhs, uv).rg;
+  vec2 b = texture(rhs, uv).rg;
+
+  // The inverse transform is unnormalized, so scale by 1/N² here
+  frag = vec4(cmul(a, b) / float(n * n), 0, 0);
+}
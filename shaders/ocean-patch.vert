@@ -0,0 +1,15 @@
+out vec2 patch_corner;
+
+uniform vec2 grid_origin;
+uniform float grid_spacing;
+uniform int grid_side;
+
+const ivec2 CORNERS[4] =
+  ivec2[](ivec2(0, 0), ivec2(1, 0), ivec2(1, 1), ivec2(0, 1));
+
+void main() {
+  int index = gl_VertexID / 4;
+  ivec2 cell = ivec2(index % grid_side, index / grid_side);
+  patch_corner =
+    grid_origin + vec2(cell + CORNERS[gl_VertexID % 4]) * grid_spacing;
+}
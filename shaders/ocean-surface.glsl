@@ -0,0 +1,90 @@
+uniform sampler2D heightmap;
+uniform sampler2D wake;
+uniform vec2 wake_origin;
+uniform float wake_size;
+uniform sampler2D kelvin;
+uniform vec2 kelvin_origin;
+uniform float kelvin_size;
+uniform sampler2D bathymetry; // r: depth, gb: heightmap sampling offset
+uniform vec2 bathymetry_origin;
+uniform float bathymetry_size;
+uniform float peak_wavenumber;
+
+const int N = 256;
+// Keep in sync with bathymetry.rs
+const float DEEP = 10000.0;
+const float MAX_KD = 10.0;
+const float SHORE_FADE = 1.0;
+const float MAX_SHOALING = 2.0;
+
+// Height from a map covering a square area of the world, zero outside it
+float local_height(sampler2D map, vec2 origin, float size, vec2 position) {
+  vec2 uv = (position - origin) / size;
+  if (any(lessThan(uv, vec2(0.0))) || any(greaterThan(uv, vec2(1.0)))) {
+    return 0.0;
+  }
+  return texture(map, uv).r;
+}
+
+vec4 seabed_at(vec2 position) {
+  vec2 uv = (position - bathymetry_origin) / bathymetry_size;
+  if (any(lessThan(uv, vec2(0.0))) || any(greaterThan(uv, vec2(1.0)))) {
+    return vec4(DEEP, 0.0, 0.0, 0.0);
+  }
+  return texture(bathymetry, uv);
+}
+
+// Local wavenumber at `depth`, by Eckart's approximation
+float local_wavenumber(float depth, float k0) {
+  return k0 / sqrt(tanh(min(k0 * depth, MAX_KD)));
+}
+
+// Deep water `height` raised by shoaling, sharpened into Stokes crests and
+// limited by breaking, like `bathymetry::shoal`
+float shoal(float height, float depth) {
+  if (depth <= 0.0) {
+    return 0.0;
+  }
+  float k = local_wavenumber(depth, peak_wavenumber);
+  float kd = min(k * depth, MAX_KD);
+  float sigma = tanh(kd);
+
+  float group = sigma * (1.0 + 2.0 * kd / sinh(2.0 * kd));
+  height /= sqrt(max(group, 1.0 / (MAX_SHOALING * MAX_SHOALING)));
+
+  sigma = max(sigma, 0.3);
+  float stokes = (3.0 - sigma * sigma) / (4.0 * sigma * sigma * sigma) - 0.5;
+  float crest = min(k * height * height * stokes, abs(height) / 2.0);
+
+  float limit = 0.39 * depth;
+  height = clamp(height + crest, -limit, limit);
+
+  return height * smoothstep(0.0, SHORE_FADE, depth);
+}
+
+vec3 surface_at(vec2 position) {
+  vec4 seabed = seabed_at(position);
+  vec2 uv = (position + seabed.gb) / N;
+  vec3 result;
+  result.x = position.x;
+  result.y = shoal(texture(heightmap, mod(uv, 1)).r, seabed.r)
+    + local_height(wake, wake_origin, wake_size, position)
+    + local_height(kelvin, kelvin_origin, kelvin_size, position);
+  result.z = position.y;
+  return result;
+}
+
+// Normal from the heights one unit away on either side, with y and z swapped
+// as `ocean.frag` expects
+vec3 surface_normal(vec2 position) {
+  float height_left  = surface_at(position - vec2(1.0, 0.0)).y;
+  float height_right = surface_at(position + vec2(1.0, 0.0)).y;
+  float height_up    = surface_at(position - vec2(0.0, 1.0)).y;
+  float height_down  = surface_at(position + vec2(0.0, 1.0)).y;
+
+  vec3 normal;
+  normal.x = height_left - height_right;
+  normal.y = height_up - height_down;
+  normal.z = 2.0;
+  return normalize(normal);
+}
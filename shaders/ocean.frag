@@ -1,12 +1,99 @@
 in float height;
 in vec3 normal;
+in vec3 world_position;
+in float water_depth;
+
+uniform vec3 eye;
+// Area drawn by a finer clipmap level, as min.xy and max.xy
+uniform vec4 hole;
+uniform samplerCube environment;
+uniform float roughness;
+uniform vec3 shallow_color;
+uniform float scatter_depth;
+uniform vec3 subsurface_color;
+uniform float subsurface_strength;
+uniform float subsurface_height;
+// Set when the eye is below the surface, which is then seen from beneath
+uniform int underwater;
 
 out vec4 frag;
 
-const vec3 light_dir = vec3(1.0, 0.25, 0.0);
+const float PI = 3.14159265358979;
+
+// Schlick's approximation of the Fresnel reflectance
+float fresnel(float cos_theta) {
+  float f0 = pow((refractive_index - 1.0) / (refractive_index + 1.0), 2.0);
+  return f0 + (1.0 - f0) * pow(1.0 - clamp(cos_theta, 0.0, 1.0), 5.0);
+}
+
+// GGX distribution with Smith shadowing, the common microfacet model
+float specular(vec3 n, vec3 v, vec3 l) {
+  vec3 h = normalize(v + l);
+  float alpha = max(roughness * roughness, 0.001);
+  float alpha2 = alpha * alpha;
+  float n_h = max(dot(n, h), 0.0);
+  float n_v = max(dot(n, v), 0.001);
+  float n_l = max(dot(n, l), 0.0);
+  float d = n_h * n_h * (alpha2 - 1.0) + 1.0;
+  float distribution = alpha2 / (PI * d * d);
+  float k = alpha / 2.0;
+  float shadowing = n_v / (n_v * (1.0 - k) + k) * n_l / (n_l * (1.0 - k) + k);
+  return distribution * shadowing * fresnel(dot(v, h)) / (4.0 * n_v);
+}
 
 void main() {
-  vec3 world_normal = normal.xzy;
-  vec3 dir = normalize(light_dir);
-  frag = vec4(vec3(max(0.0, dot(dir, world_normal))), 1.0);
+  vec2 grid = world_position.xz;
+  if (all(greaterThan(grid, hole.xy)) && all(lessThan(grid, hole.zw))) {
+    discard;
+  }
+
+  vec3 n = normalize(normal.xzy);
+  vec3 v = normalize(eye - world_position);
+  vec3 l = normalize(sun_direction);
+  // Rougher surfaces reflect blurrier mips of a prefiltered environment
+  float levels = log2(float(textureSize(environment, 0).x));
+
+  if (underwater != 0) {
+    // Only light from within Snell's window refracts down through the
+    // surface. Beyond it the surface mirrors the depths below.
+    vec3 below = -n;
+    vec3 transmitted = refract(-v, below, refractive_index);
+    vec3 color = water_color();
+    if (dot(transmitted, transmitted) > 0.0) {
+      vec3 above = textureLod(environment, transmitted, roughness * levels).rgb;
+      float cos_transmitted = dot(transmitted, -below);
+      color = mix(above, water_color(), fresnel(cos_transmitted));
+    }
+    float reach = length(eye - world_position);
+    color = absorb(color, reach) + god_rays(eye, -v, reach);
+    frag = vec4(color, 1.0);
+    return;
+  }
+
+  float reflectance = fresnel(dot(n, v));
+  vec3 reflection =
+      textureLod(environment, reflect(-v, n), roughness * levels).rgb;
+  vec3 highlight = sun_radiance * specular(n, v, l);
+
+  // Looking across the water the view passes through more of it than
+  // looking straight down
+  float path = water_depth / max(dot(n, v), 0.1);
+  float deepness = 1.0 - exp(-path / scatter_depth);
+  vec3 scatter = mix(shallow_color, deep_color, deepness);
+  scatter *= ambient() + sun_radiance * max(dot(n, l), 0.0) / PI;
+
+  // Crests glow where the sun shines through them towards the viewer
+  float crest = clamp(height / subsurface_height, 0.0, 1.0);
+  float behind = pow(max(dot(v, -l), 0.0), 4.0);
+  vec3 subsurface = subsurface_color * subsurface_strength * crest
+    * (sun_radiance * behind / PI + ambient() * 0.2);
+
+  vec3 color = mix(scatter + subsurface, reflection, reflectance) + highlight;
+
+  // Far water fades into the sky it meets at the horizon
+  vec3 ahead = vec3(-v.x, 0.0, -v.z);
+  ahead = length(ahead) > 0.0001 ? normalize(ahead) : vec3(1.0, 0.0, 0.0);
+  vec3 horizon = textureLod(environment, ahead, 0.0).rgb;
+  color = mix(color, fog_color(horizon, -v), fog(eye, world_position));
+  frag = vec4(color, 1.0);
 }
@@ -1,39 +1,64 @@
 out float height;
 out vec3 normal;
+out vec3 world_position;
+out float water_depth;
 
-uniform sampler2D heightmap;
 uniform mat4 view_projection;
-uniform vec2 offset;
+uniform vec2 grid_origin;
+uniform float grid_spacing;
+uniform int grid_side;
+// Distances from the eye over which odd vertices slide onto even ones
+uniform vec2 morph_range;
+uniform vec3 eye;
+uniform int projected_grid;
+uniform mat4 inverse_view_projection;
 
-const int N = 256;
+// How far past the screen edges the projected grid reaches, so waves moved
+// sideways don't pull the mesh away from them
+const float GRID_MARGIN = 1.2;
 
-vec3 position_at_coordinates(int x, int y) {
-  vec2 position = vec2(x, y) + offset * N;
-  vec2 uv = position / N;
-  vec3 result;
-  result.x = position.x;
-  result.y = texture(heightmap, mod(uv, 1)).r;
-  result.z = position.y;
-  return result;
+// Where the ray through a point of the screen meets the sea plane. Rays that
+// miss it, above the horizon, end on the far plane straight above the sea.
+vec2 project_to_sea(vec2 screen) {
+  vec4 near = inverse_view_projection * vec4(screen, -1.0, 1.0);
+  vec4 far = inverse_view_projection * vec4(screen, 1.0, 1.0);
+  near /= near.w;
+  far /= far.w;
+  float t = near.y / (near.y - far.y);
+  if (t < 0.0 || t > 1.0) {
+    t = 0.99;
+  }
+  return mix(near.xz, far.xz, t);
+}
+
+vec2 grid_position(int x, int y) {
+  if (projected_grid != 0) {
+    vec2 screen = (vec2(x, y) / grid_side * 2.0 - 1.0) * GRID_MARGIN;
+    return project_to_sea(screen);
+  }
+  vec2 position = grid_origin + vec2(x, y) * grid_spacing;
+
+  // At the end of the morph range the grid has become the next coarser one,
+  // so its edge lines up with that level's vertices
+  vec2 to_eye = abs(position - eye.xz);
+  float distance = max(to_eye.x, to_eye.y);
+  float morph = clamp(
+    (distance - morph_range.x) / (morph_range.y - morph_range.x), 0.0, 1.0
+  );
+  vec2 odd = mod(vec2(x, y), 2.0);
+  return position - odd * grid_spacing * morph;
 }
 
 void main() {
-  vec2 size = textureSize(heightmap, 0);
-  int line_count = N + 1;
+  int line_count = grid_side + 1;
   int x = gl_VertexID / line_count;
   int y = gl_VertexID % line_count;
-  vec3 position = position_at_coordinates(x, y);
-
-  float height_left  = position_at_coordinates(x - 1, y).y;
-  float height_right = position_at_coordinates(x + 1, y).y;
-  float height_up    = position_at_coordinates(x, y - 1).y;
-  float height_down  = position_at_coordinates(x, y + 1).y;
-
-  normal.x = height_left - height_right;
-  normal.y = height_down - height_up;
-  normal.z = 2.0;
-  normal = normalize(normal);
+  vec2 grid = grid_position(x, y);
+  vec3 position = surface_at(grid);
 
+  normal = surface_normal(grid);
   height = position.y;
+  world_position = position;
+  water_depth = seabed_at(position.xz).r + position.y;
   gl_Position = view_projection * vec4(position, 1.0);
 }
@@ -0,0 +1,9 @@
+in vec3 direction;
+
+uniform samplerCube environment;
+
+out vec4 frag;
+
+void main() {
+  frag = vec4(textureLod(environment, normalize(direction), 0.0).rgb, 1.0);
+}
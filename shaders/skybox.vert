@@ -0,0 +1,13 @@
+out vec3 direction;
+
+uniform mat4 inverse_view_projection;
+
+void main() {
+  vec2 corner = vec2(gl_VertexID % 2, gl_VertexID / 2) * 2.0 - 1.0;
+  // Just short of the far plane, so everything else draws over it
+  gl_Position = vec4(corner, 0.99999, 1.0);
+
+  vec4 near = inverse_view_projection * vec4(corner, -1.0, 1.0);
+  vec4 far = inverse_view_projection * vec4(corner, 1.0, 1.0);
+  direction = far.xyz / far.w - near.xyz / near.w;
+}
@@ -0,0 +1,96 @@
+in vec2 uv;
+
+uniform sampler2D positions;  // xyz: position, w: remaining life
+uniform sampler2D velocities; // xyz: velocity, w: size
+uniform sampler2D heightmap;
+uniform sampler2D velocity;   // r: x, g: z, b: y, a: vertical acceleration
+uniform sampler2D jacobian;   // r: dDx/dx, g: dDz/dz, b: dDx/dz
+uniform uint frame;
+uniform float dt;
+uniform vec2 center;
+uniform float choppiness;
+uniform float jacobian_threshold;
+uniform float acceleration_threshold; // as a fraction of g
+uniform float spawn_rate;
+uniform float lifetime;
+uniform float drag;
+uniform float kick;
+uniform float size;
+uniform float growth;
+
+layout (location = 0) out vec4 position_out;
+layout (location = 1) out vec4 velocity_out;
+
+const float g = 9.81;
+
+uint hash(uint x) {
+  x ^= x >> 16;
+  x *= 0x7feb352du;
+  x ^= x >> 15;
+  x *= 0x846ca68bu;
+  x ^= x >> 16;
+  return x;
+}
+
+// Uniform in [0, 1), advancing `seed`
+float random(inout uint seed) {
+  seed = hash(seed);
+  return float(seed >> 8) / 16777216.0;
+}
+
+// Like breaking.frag, except that either measure alone is enough for spray
+float breaking_intensity(vec2 uv) {
+  vec3 d = texture(jacobian, uv).rgb * choppiness;
+  float determinant = (1.0 - d.x) * (1.0 - d.y) - d.z * d.z;
+  float acceleration = texture(velocity, uv).a;
+  float folding = max(jacobian_threshold - determinant, 0.0);
+  float falling = max(-acceleration / g - acceleration_threshold, 0.0);
+  return folding + falling;
+}
+
+void main() {
+  ivec2 texel = ivec2(gl_FragCoord.xy);
+  vec4 state = texelFetch(positions, texel, 0);
+  vec4 motion = texelFetch(velocities, texel, 0);
+  float n = float(textureSize(heightmap, 0).x);
+
+  if (state.w <= 0.0) {
+    // A free particle tries one random spot of the surface per frame
+    uint id = uint(texel.y * textureSize(positions, 0).x + texel.x);
+    uint seed = hash(id ^ hash(frame));
+    vec2 xz = center + (vec2(random(seed), random(seed)) - 0.5) * n;
+    vec2 surface_uv = mod(xz / n, 1.0);
+    float intensity = breaking_intensity(surface_uv);
+    if (random(seed) >= intensity * spawn_rate * dt) {
+      position_out = vec4(0.0);
+      velocity_out = vec4(0.0);
+      return;
+    }
+
+    vec3 surface_velocity = texture(velocity, surface_uv).rbg;
+    vec3 jitter = vec3(random(seed), random(seed), random(seed)) - 0.5;
+    float height = texture(heightmap, surface_uv).r;
+    float life = lifetime * (0.5 + 0.5 * random(seed));
+    position_out = vec4(xz.x, height, xz.y, life);
+    velocity_out = vec4(
+      surface_velocity + vec3(0.0, kick * intensity, 0.0) + jitter,
+      size * (0.5 + 0.5 * random(seed))
+    );
+    return;
+  }
+
+  vec3 v = motion.xyz;
+  v.y -= g * dt;
+  v -= v * min(drag * dt, 1.0);
+  vec3 position = state.xyz + v * dt;
+  float life = state.w - dt;
+
+  // Back in the water
+  float surface = texture(heightmap, mod(position.xz / n, 1.0)).r;
+  if (position.y < surface && v.y < 0.0) {
+    life = 0.0;
+  }
+
+  position_out = vec4(position, max(life, 0.0));
+  velocity_out = vec4(v, motion.w + growth * dt);
+}
@@ -0,0 +1,16 @@
+in vec2 corner;
+in float opacity;
+
+out vec4 frag;
+
+const vec3 albedo = vec3(0.9, 0.95, 1.0);
+
+void main() {
+  float falloff = 1.0 - dot(corner, corner);
+  if (falloff <= 0.0) {
+    discard;
+  }
+  // Droplets scatter light every which way, so no normal is needed
+  vec3 color = albedo * (ambient() + sun_radiance / 4.0);
+  frag = vec4(color, opacity * falloff * falloff * 0.6);
+}
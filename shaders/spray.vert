@@ -0,0 +1,29 @@
+out vec2 corner;
+out float opacity;
+
+uniform sampler2D positions;  // xyz: position, w: remaining life
+uniform sampler2D velocities; // xyz: velocity, w: size
+uniform mat4 view;
+uniform mat4 projection;
+
+void main() {
+  int side = textureSize(positions, 0).x;
+  ivec2 texel = ivec2(gl_InstanceID % side, gl_InstanceID / side);
+  vec4 state = texelFetch(positions, texel, 0);
+  float size = texelFetch(velocities, texel, 0).w;
+
+  corner = vec2(gl_VertexID % 2, gl_VertexID / 2) * 2.0 - 1.0;
+  if (state.w <= 0.0) {
+    // Free particles collapse to a point and draw nothing
+    gl_Position = vec4(0.0);
+    opacity = 0.0;
+    return;
+  }
+
+  // Fade out over the last half second of life
+  opacity = clamp(state.w * 2.0, 0.0, 1.0);
+
+  vec4 center = view * vec4(state.xyz, 1.0);
+  center.xy += corner * size;
+  gl_Position = projection * center;
+}
@@ -0,0 +1,64 @@
+// Needs ocean-surface.glsl before it
+in vec3 near;
+in vec3 direction;
+
+uniform mat4 inverse_view_projection;
+// Set for the overlay drawn over everything at the waterline, rather than
+// the water behind everything else
+uniform int waterline;
+
+out vec4 frag;
+
+const vec3 SAND = vec3(0.76, 0.7, 0.5);
+const int SEABED_STEPS = 48;
+// How far the seabed is looked for, past which the water hides it anyway
+const float SEABED_DISTANCE = 120.0;
+// Height of the band darkened where the surface crosses the near plane
+const float WATERLINE_WIDTH = 0.02;
+
+// How far below the surface `position` is, with the surface shoaled and
+// stirred by wakes just as the ocean mesh draws it
+float submersion(vec3 position) {
+  return surface_at(position.xz).y - position.y;
+}
+
+void main() {
+  float below = submersion(near);
+
+  if (waterline != 0) {
+    // A dark meniscus where the surface meets the lens, and a veil of water
+    // over the part of the view that has dipped under while the eye hasn't
+    vec4 center = inverse_view_projection * vec4(0.0, 0.0, -1.0, 1.0);
+    bool eye_below = submersion(center.xyz / center.w) > 0.0;
+    float meniscus = 1.0 - smoothstep(0.0, WATERLINE_WIDTH, abs(below));
+    float veil = !eye_below && below > 0.0 ? 0.6 : 0.0;
+    vec3 color = mix(water_color(), water_color() * 0.2, meniscus);
+    frag = vec4(color, max(meniscus, veil));
+    return;
+  }
+
+  if (below <= 0.0) {
+    discard;
+  }
+
+  vec3 ray = normalize(direction);
+  float spacing = SEABED_DISTANCE / float(SEABED_STEPS);
+  float reach = SEABED_DISTANCE;
+  vec3 color = water_color();
+  for (int i = 1; i <= SEABED_STEPS; i++) {
+    vec3 position = near + ray * spacing * float(i);
+    if (position.y < -seabed_at(position.xz).r) {
+      reach = spacing * float(i);
+      float depth = max(-position.y, 0.0);
+      vec3 sun = refracted_sun();
+      vec3 sunlight = sun_radiance * exp(-absorption * depth / -sun.y)
+        * caustics(position) * max(-sun.y, 0.0);
+      vec3 skylight = ambient() * exp(-absorption * depth);
+      color = SAND * (skylight + sunlight * INV_PI);
+      break;
+    }
+  }
+
+  color = absorb(color, reach) + god_rays(near, ray, reach);
+  frag = vec4(color, 1.0);
+}
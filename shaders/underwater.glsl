@@ -0,0 +1,71 @@
+// Light under the surface. Needs lighting.glsl before it; the uniforms are
+// set from `WaterMaterial`, see material.rs
+uniform vec3 deep_color;
+uniform vec3 absorption;
+uniform float refractive_index;
+// Set from `Caustics`, see caustics.rs
+uniform sampler2D caustics_map;
+uniform float caustics_depth;
+
+const float INV_PI = 0.318309886;
+const int GOD_RAY_STEPS = 16;
+// How far along the view god rays are gathered, in world units
+const float GOD_RAY_DISTANCE = 60.0;
+// Fraction of the light in a shaft scattered toward the eye per world unit
+const float GOD_RAY_SCATTERING = 0.05;
+
+// Water lit from above, seen through so much of it that nothing else shows
+vec3 water_color() {
+  float overhead = max(normalize(sun_direction).y, 0.0);
+  return deep_color * (ambient() + sun_radiance * overhead * INV_PI);
+}
+
+// Sunlight direction after refracting into calm water, with the sun kept
+// above the horizon like `Caustics` keeps it
+vec3 refracted_sun() {
+  vec3 sun = normalize(sun_direction);
+  vec3 incoming = -normalize(vec3(sun.x, max(sun.y, 0.1), sun.z));
+  return refract(incoming, vec3(0.0, 1.0, 0.0), 1.0 / refractive_index);
+}
+
+// `color` seen through `reach` world units of water, which absorbs red
+// first and scatters its own colour in
+vec3 absorb(vec3 color, float reach) {
+  vec3 transmittance = exp(-absorption * reach);
+  return color * transmittance + water_color() * (1.0 - transmittance);
+}
+
+// Sunlight at `position` relative to calm water, from the texture made by
+// `Caustics`, which is sharpest at its depth and fades in from the surface
+float caustics(vec3 position) {
+  vec3 sun = refracted_sun();
+  float depth = max(-position.y, 0.0);
+  vec2 above = position.xz + sun.xz * depth / sun.y;
+
+  float side = float(textureSize(caustics_map, 0).x);
+  float focused = texture(caustics_map, mod(above / side, 1.0)).r;
+  float sharpness = min(depth / caustics_depth, 1.0);
+  return min(mix(1.0, focused, sharpness), 4.0);
+}
+
+// Sunlight scattered toward `eye` from shafts under focusing crests, along
+// `direction` up to `reach`
+vec3 god_rays(vec3 eye, vec3 direction, float reach) {
+  vec3 sun = refracted_sun();
+  float spacing = min(reach, GOD_RAY_DISTANCE) / float(GOD_RAY_STEPS);
+  // `sun` travels down, so looking back up along it faces the light
+  float forward = pow(max(dot(direction, -sun), 0.0), 4.0);
+
+  vec3 sum = vec3(0.0);
+  for (int i = 0; i < GOD_RAY_STEPS; i++) {
+    float along = (float(i) + 0.5) * spacing;
+    vec3 position = eye + direction * along;
+    if (position.y > 0.0) {
+      continue;
+    }
+    float path = -position.y / -sun.y + along;
+    vec3 light = exp(-absorption * path);
+    sum += light * max(caustics(position) - 1.0, 0.0);
+  }
+  return sun_radiance * sum * spacing * GOD_RAY_SCATTERING * (0.2 + forward);
+}
@@ -0,0 +1,15 @@
+out vec3 near;
+out vec3 direction;
+
+uniform mat4 inverse_view_projection;
+uniform float depth;
+
+void main() {
+  vec2 corner = vec2(gl_VertexID % 2, gl_VertexID / 2) * 2.0 - 1.0;
+  gl_Position = vec4(corner, depth, 1.0);
+
+  vec4 near_point = inverse_view_projection * vec4(corner, -1.0, 1.0);
+  vec4 far_point = inverse_view_projection * vec4(corner, 1.0, 1.0);
+  near = near_point.xyz / near_point.w;
+  direction = far_point.xyz / far_point.w - near;
+}
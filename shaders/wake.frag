@@ -0,0 +1,34 @@
+in vec2 uv;
+
+uniform sampler2D previous; // r: height, g: height one step earlier
+uniform sampler2D source;
+uniform float source_strength;
+uniform float courant; // (wave speed * dt / texel size)²
+uniform float damping;
+uniform vec2 advection; // how far the water drifts in one step, in uv
+uniform float step_ratio; // length of this step over that of the last one
+
+out vec4 frag;
+
+void main() {
+  vec2 texel = 1.0 / textureSize(previous, 0);
+
+  // Semi-Lagrangian advection: take the state from upstream
+  vec2 at = uv - advection;
+  vec2 state = texture(previous, at).rg;
+  float left  = texture(previous, at - vec2(texel.x, 0.0)).r;
+  float right = texture(previous, at + vec2(texel.x, 0.0)).r;
+  float down  = texture(previous, at - vec2(0.0, texel.y)).r;
+  float up    = texture(previous, at + vec2(0.0, texel.y)).r;
+
+  float laplacian = left + right + up + down - 4.0 * state.r;
+  float change = (state.r - state.g) * step_ratio;
+  float height = (state.r + change + courant * laplacian) * damping;
+  height += texture(source, uv).r * source_strength;
+
+  // Let ripples die out before they reach the edge instead of reflecting
+  vec2 edge = min(uv, 1.0 - uv) / 0.05;
+  height *= clamp(min(edge.x, edge.y), 0.0, 1.0);
+
+  frag = vec4(height, state.r, 0.0, 1.0);
+}
@@ -0,0 +1,256 @@
+use crate::fft::Spectrum;
+use luminance::{
+    context::GraphicsContext,
+    pixel::RGBA32F,
+    texture::{Dim2, Flat, GenMipmaps, Texture},
+};
+
+/// Depth treated as open ocean, where the seabed has no effect on the waves
+const DEEP: f32 = 10000.0;
+/// Largest product of wavenumber and depth fed to tanh and sinh. Past it
+/// tanh is 1 to within f32 precision, and GLSL's overflows into NaN.
+const MAX_KD: f32 = 10.0;
+/// Most the shallows may shorten a wave, as a factor of its deep wavelength
+const MAX_COMPRESSION: f32 = 4.0;
+/// Depth over which waves fade out towards the shoreline
+const SHORE_FADE: f32 = 1.0;
+/// Most shoaling may raise a wave, as a factor of its deep height
+const MAX_SHOALING: f32 = 2.0;
+/// Most the shallows may raise a wave, crest included, as a factor of its
+/// deep height
+pub const MAX_GAIN: f32 = MAX_SHOALING * 1.5;
+
+pub type BathymetryTexture = Texture<Flat, Dim2, RGBA32F>;
+
+/// Seabed depths over a square area of the world. Waves coming in from the
+/// open ocean slow down, shorten and steepen over the shallows, and die out
+/// on land. Outside the area the water is deep.
+pub struct Bathymetry {
+    origin: glm::Vec2,
+    size: f32,
+    side: usize,
+    /// Depth below the rest surface per texel, zero or less on land
+    depths: Vec<f32>,
+    /// Offset to where each texel samples the deep water heightmap
+    offsets: Vec<glm::Vec2>,
+    peak_wavenumber: f32,
+    /// Depth in red, offset in green and blue
+    texture: BathymetryTexture,
+}
+
+impl Bathymetry {
+    /// `depths` is a square map, row by row, covering the area from `origin`
+    /// to `origin + size` in world x and z.
+    pub fn new(
+        context: &mut impl GraphicsContext,
+        spectrum: &Spectrum,
+        depths: Vec<f32>,
+        origin: glm::Vec2,
+        size: f32,
+    ) -> Self {
+        let side = (depths.len() as f64).sqrt() as usize;
+        assert_eq!(side * side, depths.len(), "depth map must be square");
+        let peak_wavenumber = spectrum.peak_wavenumber();
+
+        let mut bathymetry = Self {
+            origin,
+            size,
+            side,
+            depths,
+            offsets: Vec::new(),
+            peak_wavenumber,
+            texture: Texture::new(
+                context,
+                [side as u32, side as u32],
+                0,
+                &Default::default(),
+            )
+            .unwrap(),
+        };
+        bathymetry.offsets = bathymetry.trace_offsets(&spectrum.direction);
+
+        let texels: Vec<_> = bathymetry
+            .depths
+            .iter()
+            .zip(&bathymetry.offsets)
+            .map(|(&depth, offset)| (depth, offset.x, offset.y, 0.0))
+            .collect();
+        bathymetry.texture.upload(GenMipmaps::No, &texels);
+        bathymetry
+    }
+
+    /// Open ocean everywhere.
+    pub fn deep(
+        context: &mut impl GraphicsContext,
+        spectrum: &Spectrum,
+    ) -> Self {
+        Self::new(context, spectrum, vec![DEEP], glm::zero(), 1.0)
+    }
+
+    pub fn origin(&self) -> glm::Vec2 {
+        self.origin
+    }
+
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    pub fn texture(&self) -> &BathymetryTexture {
+        &self.texture
+    }
+
+    /// Depth of the seabed below the rest surface at world `x`, `z`.
+    pub fn depth_at(&self, x: f32, z: f32) -> f32 {
+        self.sample(&self.depths, x, z, DEEP)
+    }
+
+    /// Where to sample the deep water heightmap for the surface at world
+    /// `x`, `z`, so waves shorten as they run into the shallows.
+    pub fn warp(&self, x: f32, z: f32) -> glm::Vec2 {
+        glm::vec2(x, z) + self.sample(&self.offsets, x, z, glm::zero())
+    }
+
+    /// Turns the deep water `height` into the height at world `x`, `z`.
+    pub fn shoal(&self, height: f32, x: f32, z: f32) -> f32 {
+        shoal(height, self.depth_at(x, z), self.peak_wavenumber)
+    }
+
+    /// Sums up how much the waves have been shortened on their way to each
+    /// texel, marching against the direction they travel.
+    fn trace_offsets(&self, direction: &glm::Vec2) -> Vec<glm::Vec2> {
+        let direction = glm::normalize(direction);
+        let step = self.size / self.side as f32;
+        let mut offsets = Vec::with_capacity(self.side * self.side);
+        for y in 0..self.side {
+            for x in 0..self.side {
+                let mut position = self.texel_center(x, y);
+                let mut offset = 0.0;
+                while self.contains(position) {
+                    let depth = self.depth_at(position.x, position.y);
+                    let compression = compression(depth, self.peak_wavenumber);
+                    offset += (compression - 1.0) * step;
+                    position -= direction * step;
+                }
+                offsets.push(direction * offset);
+            }
+        }
+        offsets
+    }
+
+    fn texel_center(&self, x: usize, y: usize) -> glm::Vec2 {
+        let texel = glm::vec2(x as f32 + 0.5, y as f32 + 0.5);
+        self.origin + texel * (self.size / self.side as f32)
+    }
+
+    fn contains(&self, position: glm::Vec2) -> bool {
+        let uv = (position - self.origin) / self.size;
+        (0.0..=1.0).contains(&uv.x) && (0.0..=1.0).contains(&uv.y)
+    }
+
+    /// Samples `map` like `ocean-surface.glsl` samples the bathymetry
+    /// texture, returning `outside` beyond the edges of the area.
+    fn sample<T>(&self, map: &[T], x: f32, z: f32, outside: T) -> T
+    where
+        T: Copy + std::ops::Add<Output = T> + std::ops::Mul<f32, Output = T>,
+    {
+        let position = glm::vec2(x, z);
+        if !self.contains(position) {
+            return outside;
+        }
+        let side = self.side as f32;
+        let texel = (position - self.origin) / self.size * side;
+        let (x, y) = (texel.x - 0.5, texel.y - 0.5);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let at = |x: f32, y: f32| {
+            let x = x.clamp(0.0, side - 1.0) as usize;
+            let y = y.clamp(0.0, side - 1.0) as usize;
+            map[y * self.side + x]
+        };
+        let lerp = |a: T, b: T, t: f32| a * (1.0 - t) + b * t;
+        let top = lerp(at(x0, y0), at(x0 + 1.0, y0), fx);
+        let bottom = lerp(at(x0, y0 + 1.0), at(x0 + 1.0, y0 + 1.0), fx);
+        lerp(top, bottom, fy)
+    }
+}
+
+/// Local wavenumber of a wave with deep water wavenumber `k0`, at `depth`.
+/// Eckart's approximation of the dispersion relation.
+fn local_wavenumber(depth: f32, k0: f32) -> f32 {
+    k0 / (k0 * depth).min(MAX_KD).tanh().sqrt()
+}
+
+/// How much shorter a wave is at `depth` than in deep water.
+fn compression(depth: f32, k0: f32) -> f32 {
+    if depth <= 0.0 {
+        return MAX_COMPRESSION;
+    }
+    (local_wavenumber(depth, k0) / k0).min(MAX_COMPRESSION)
+}
+
+/// The deep water `height` of a wave with wavenumber `k0`, raised by
+/// shoaling, sharpened into Stokes crests and limited by breaking, at
+/// `depth`. Matches `shoal` in `ocean-surface.glsl`.
+pub fn shoal(height: f32, depth: f32, k0: f32) -> f32 {
+    if depth <= 0.0 {
+        return 0.0;
+    }
+    let k = local_wavenumber(depth, k0);
+    let kd = (k * depth).min(MAX_KD);
+    let sigma = kd.tanh();
+
+    // Energy flux is kept as the group velocity drops
+    let group = sigma * (1.0 + 2.0 * kd / (2.0 * kd).sinh());
+    let height = height / group.max(MAX_SHOALING.powi(-2)).sqrt();
+
+    // Second order Stokes term, relative to its deep water value
+    let sigma = sigma.max(0.3);
+    let stokes = (3.0 - sigma * sigma) / (4.0 * sigma.powi(3)) - 0.5;
+    let crest = (k * height * height * stokes).min(height.abs() / 2.0);
+
+    // Waves break once they grow higher than about 0.78 times the depth
+    let limit = 0.39 * depth;
+    let height = (height + crest).clamp(-limit, limit);
+
+    let fade = (depth / SHORE_FADE).min(1.0);
+    height * fade * fade * (3.0 - 2.0 * fade)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const K0: f32 = 0.1;
+
+    #[test]
+    fn leaves_deep_water_alone() {
+        for &height in &[-3.0, -0.5, 0.0, 0.5, 3.0] {
+            assert!((shoal(height, DEEP, K0) - height).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn flattens_waves_on_land() {
+        assert_eq!(shoal(2.0, 0.0, K0), 0.0);
+        assert_eq!(shoal(-2.0, -5.0, K0), 0.0);
+    }
+
+    #[test]
+    fn raises_and_sharpens_crests_in_shallow_water() {
+        let crest = shoal(0.2, 2.0, K0);
+        let trough = shoal(-0.2, 2.0, K0);
+        assert!(crest > 0.2);
+        assert!(crest > -trough && trough < 0.0);
+    }
+
+    #[test]
+    fn stays_within_the_gain_and_breaking_limits() {
+        for depth in (1..200).map(|i| i as f32 * 0.25) {
+            for height in (-40..=40).map(|i| i as f32 * 0.1) {
+                let shoaled = shoal(height, depth, K0);
+                assert!(shoaled.abs() <= height.abs() * MAX_GAIN + 1e-5);
+                assert!(shoaled.abs() <= 0.39 * depth + 1e-5);
+            }
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use crate::lighting::Lighting;
+use crate::physics::RigidBody;
+use luminance::{
+    context::GraphicsContext,
+    linear::M44,
+    pipeline::ShadingGate,
+    render_state::RenderState,
+    shader::program::{Program, Uniform},
+    tess::{Mode, Tess, TessBuilder},
+};
+use luminance_derive::UniformInterface;
+
+#[derive(UniformInterface)]
+struct BodyInterface {
+    view_projection: Uniform<M44>,
+    model: Uniform<M44>,
+    half_extents: Uniform<[f32; 3]>,
+    sun_direction: Uniform<[f32; 3]>,
+    sun_radiance: Uniform<[f32; 3]>,
+    sky_color: Uniform<[f32; 3]>,
+    horizon_color: Uniform<[f32; 3]>,
+}
+
+/// Draws floating bodies as plain boxes, lit by the sun and the sky.
+pub struct BodyRenderer {
+    shader: Program<(), (), BodyInterface>,
+    tess: Tess,
+}
+
+impl BodyRenderer {
+    pub fn new(context: &mut impl GraphicsContext) -> Self {
+        let shader = crate::shader::from_strings(
+            include_str!("../shaders/body.vert"),
+            concat!(
+                include_str!("../shaders/lighting.glsl"),
+                include_str!("../shaders/body.frag"),
+            ),
+        );
+        // Two triangles for each face of a cube, built in the vertex shader
+        let tess = TessBuilder::new(context)
+            .set_mode(Mode::Triangle)
+            .set_vertex_nb(36)
+            .build()
+            .unwrap();
+        Self { shader, tess }
+    }
+
+    /// Draws each of `bodies` as the box around its sample points.
+    pub fn render(
+        &self,
+        context: &mut impl GraphicsContext,
+        shader_gate: &ShadingGate,
+        view_projection: glm::Mat4,
+        bodies: &[RigidBody],
+        lighting: &Lighting,
+    ) {
+        shader_gate.shade(&self.shader, |render_gate, iface| {
+            iface.view_projection.update(view_projection.into());
+            iface.sun_direction.update(lighting.sun_direction.into());
+            iface.sun_radiance.update(lighting.sun_radiance().into());
+            iface.sky_color.update(lighting.sky_color.into());
+            iface.horizon_color.update(lighting.horizon_color.into());
+            render_gate.render(RenderState::default(), |tess_gate| {
+                for body in bodies {
+                    iface.model.update(body.transform().into());
+                    iface.half_extents.update(body.half_extents().into());
+                    tess_gate.render(context, (&self.tess).into());
+                }
+            });
+        });
+    }
+}
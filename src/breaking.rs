@@ -0,0 +1,140 @@
+use crate::fft::{FftTexture, N};
+use crate::readback::AsyncReadback;
+use luminance::{
+    context::GraphicsContext,
+    framebuffer::Framebuffer,
+    pipeline::{BoundTexture, Builder},
+    pixel::{Floating, RGBA32F},
+    render_state::RenderState,
+    shader::program::{Program, Uniform},
+    tess::{Mode, Tess, TessBuilder},
+    texture::{Dim2, Flat},
+};
+use luminance_derive::UniformInterface;
+
+/// Side of the square of heightmap texels reduced to at most one event
+const CELL_SIZE: u32 = 8;
+const CELLS: u32 = N / CELL_SIZE;
+
+#[derive(UniformInterface)]
+struct BreakingInterface {
+    heightmap: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    velocity: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    jacobian: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    cell_size: Uniform<i32>,
+    choppiness: Uniform<f32>,
+    jacobian_threshold: Uniform<f32>,
+    acceleration_threshold: Uniform<f32>,
+}
+
+/// A crest breaking somewhere on the heightmap.
+#[derive(Clone, Copy, Debug)]
+pub struct BreakingEvent {
+    /// World position of the crest, within the heightmap's tile
+    pub position: glm::Vec3,
+    /// How far past both breaking thresholds the crest is
+    pub intensity: f32,
+}
+
+/// Finds breaking crests on the GPU and reads them back a frame or two later.
+/// A crest breaks where choppy waves would fold the surface over, that is
+/// where the Jacobian of their horizontal displacement drops below
+/// `jacobian_threshold`, while the water there accelerates down faster than
+/// `acceleration_threshold` times gravity. Each cell of the heightmap reports
+/// at most its strongest breaking texel.
+pub struct BreakingDetector {
+    /// How far choppy waves would be displaced horizontally, as a factor of
+    /// the displacement that goes with their height
+    pub choppiness: f32,
+    pub jacobian_threshold: f32,
+    pub acceleration_threshold: f32,
+    framebuffer: Framebuffer<Flat, Dim2, RGBA32F, ()>,
+    readback: AsyncReadback,
+    shader: Program<(), (), BreakingInterface>,
+    tess: Tess,
+}
+
+impl BreakingDetector {
+    pub fn new(context: &mut impl GraphicsContext) -> Self {
+        let framebuffer = Framebuffer::new(context, [CELLS, CELLS], 0)
+            .expect("framebuffer creation");
+        let shader = crate::shader::from_strings(
+            include_str!("../shaders/quad.vert"),
+            include_str!("../shaders/breaking.frag"),
+        );
+        let tess = TessBuilder::new(context)
+            .set_mode(Mode::TriangleStrip)
+            .set_vertex_nb(4)
+            .build()
+            .unwrap();
+
+        Self {
+            choppiness: 1.0,
+            jacobian_threshold: 0.2,
+            acceleration_threshold: 0.4,
+            framebuffer,
+            readback: AsyncReadback::rgba(context, CELLS, CELLS),
+            shader,
+            tess,
+        }
+    }
+
+    /// Looks for breaking crests in a simulated frame and queues the result
+    /// for reading back.
+    pub fn detect(
+        &mut self,
+        context: &mut impl GraphicsContext,
+        builder: &Builder,
+        heightmap: &FftTexture,
+        velocity: &FftTexture,
+        jacobian: &FftTexture,
+    ) {
+        let Self {
+            choppiness,
+            jacobian_threshold,
+            acceleration_threshold,
+            framebuffer,
+            readback,
+            shader,
+            tess,
+        } = self;
+        builder.pipeline(
+            framebuffer,
+            [0.0, 0.0, 0.0, 0.0],
+            |pipeline, shader_gate| {
+                let bound_heightmap = pipeline.bind_texture(heightmap);
+                let bound_velocity = pipeline.bind_texture(velocity);
+                let bound_jacobian = pipeline.bind_texture(jacobian);
+                shader_gate.shade(shader, |render_gate, iface| {
+                    iface.heightmap.update(&bound_heightmap);
+                    iface.velocity.update(&bound_velocity);
+                    iface.jacobian.update(&bound_jacobian);
+                    iface.cell_size.update(CELL_SIZE as i32);
+                    iface.choppiness.update(*choppiness);
+                    iface.jacobian_threshold.update(*jacobian_threshold);
+                    iface
+                        .acceleration_threshold
+                        .update(*acceleration_threshold);
+                    render_gate.render(RenderState::default(), |tess_gate| {
+                        tess_gate.render(context, tess.into());
+                    });
+                });
+            },
+        );
+        readback.issue(builder, framebuffer.color_slot());
+    }
+
+    /// The events of the oldest detection that has finished reading back.
+    pub fn poll(&mut self) -> Option<Vec<BreakingEvent>> {
+        let texels = self.readback.poll()?;
+        let events = texels
+            .chunks(4)
+            .filter(|texel| texel[0] > 0.0)
+            .map(|texel| BreakingEvent {
+                position: glm::vec3(texel[1], texel[3], texel[2]),
+                intensity: texel[0],
+            })
+            .collect();
+        Some(events)
+    }
+}
@@ -5,22 +5,24 @@ pub struct Camera {
     yaw: f32,
     orientation: glm::Mat4,
     projection: glm::Mat4,
+    far: f32,
 }
 
 impl Camera {
-    fn new(projection: glm::Mat4) -> Camera {
+    fn new(projection: glm::Mat4, far: f32) -> Camera {
         Camera {
             position: glm::zero(),
             pitch: 0.,
             yaw: 0.,
             orientation: glm::identity(),
             projection,
+            far,
         }
     }
 
     pub fn persp(aspect: f32, fov: f32, near: f32, far: f32) -> Camera {
         let projection = glm::perspective_rh(aspect, fov, near, far);
-        Camera::new(projection)
+        Camera::new(projection, far)
     }
 
     pub fn take_input(&mut self, pump: &sdl2::EventPump, delta_t: f32) {
@@ -94,4 +96,26 @@ impl Camera {
     pub fn view(&self) -> glm::Mat4 {
         glm::translate(&self.orientation, &-self.position)
     }
+
+    pub fn position(&self) -> glm::Vec3 {
+        self.position
+    }
+
+    /// Distance to the far clipping plane, beyond which nothing is drawn.
+    pub fn far(&self) -> f32 {
+        self.far
+    }
+
+    /// Origin and direction of the ray through a point on the screen, in
+    /// normalized device coordinates. The center of the screen is (0, 0).
+    pub fn ray(&self, x: f32, y: f32) -> (glm::Vec3, glm::Vec3) {
+        let inverse = glm::inverse(&(self.projection * self.view()));
+        let unproject = |z: f32| {
+            let point = inverse * glm::vec4(x, y, z, 1.0);
+            glm::vec4_to_vec3(&point) / point.w
+        };
+        let near = unproject(-1.0);
+        let far = unproject(1.0);
+        (near, glm::normalize(&(far - near)))
+    }
 }
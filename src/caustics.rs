@@ -0,0 +1,125 @@
+use crate::fft::FftTexture;
+use crate::lighting::Lighting;
+use luminance::{
+    blending::{Equation, Factor},
+    context::GraphicsContext,
+    framebuffer::Framebuffer,
+    pipeline::{BoundTexture, Builder},
+    pixel::{Floating, R32F},
+    render_state::RenderState,
+    shader::program::{Program, Uniform},
+    tess::{Mode, Tess, TessBuilder, TessSlice},
+    texture::{Dim2, Flat, Texture},
+};
+use luminance_derive::UniformInterface;
+
+const RESOLUTION: u32 = 0x100;
+/// How far light is followed past the edges of the texture, in texels.
+/// Keep in sync with caustics.vert
+const MARGIN: u32 = 16;
+
+#[derive(UniformInterface)]
+struct CausticsInterface {
+    heightmap: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    sun_direction: Uniform<[f32; 3]>,
+    refractive_index: Uniform<f32>,
+    depth: Uniform<f32>,
+    edges: Uniform<i32>,
+}
+
+pub type CausticsTexture = Texture<Flat, Dim2, R32F>;
+
+/// Sunlight focused by the waves onto a plane under the surface. A grid
+/// over the heightmap is refracted down to the plane and each of its cells
+/// adds its light where it lands, so the light gathers under crests.
+pub struct Caustics {
+    /// Depth of the plane, in world units
+    pub depth: f32,
+    framebuffer: Framebuffer<Flat, Dim2, R32F, ()>,
+    shader: Program<(), (), CausticsInterface>,
+    tess: Tess,
+    edge_tess: Tess,
+}
+
+impl Caustics {
+    pub fn new(context: &mut impl GraphicsContext) -> Self {
+        let framebuffer =
+            Framebuffer::new(context, [RESOLUTION, RESOLUTION], 0)
+                .expect("framebuffer creation");
+        let shader = crate::shader::from_strings(
+            include_str!("../shaders/caustics.vert"),
+            include_str!("../shaders/caustics.frag"),
+        );
+        let tess = TessBuilder::new(context)
+            .set_mode(Mode::Triangle)
+            .set_vertex_nb((RESOLUTION * RESOLUTION * 6) as usize)
+            .build()
+            .unwrap();
+        let edge_tess = TessBuilder::new(context)
+            .set_mode(Mode::Triangle)
+            .set_vertex_nb((MARGIN * (RESOLUTION + MARGIN) * 6) as usize)
+            .build()
+            .unwrap();
+
+        Self {
+            depth: 8.0,
+            framebuffer,
+            shader,
+            tess,
+            edge_tess,
+        }
+    }
+
+    /// Light on the plane in the red channel, one texel per heightmap texel
+    /// and tiling like it. 1 is as much as calm water lets through, and each
+    /// texel lines up with the surface above it toward the sun.
+    pub fn texture(&self) -> &CausticsTexture {
+        self.framebuffer.color_slot()
+    }
+
+    /// Refracts the sunlight in `lighting` through `heightmap` for the
+    /// water's `refractive_index`.
+    pub fn render(
+        &self,
+        context: &mut impl GraphicsContext,
+        builder: &Builder,
+        heightmap: &FftTexture,
+        refractive_index: f32,
+        lighting: &Lighting,
+    ) {
+        let Self {
+            depth,
+            framebuffer,
+            shader,
+            tess,
+            edge_tess,
+        } = self;
+        builder.pipeline(
+            framebuffer,
+            [0.0, 0.0, 0.0, 0.0],
+            |pipeline, shader_gate| {
+                let bound_heightmap = pipeline.bind_texture(heightmap);
+                shader_gate.shade(shader, |render_gate, iface| {
+                    iface.heightmap.update(&bound_heightmap);
+                    iface.sun_direction.update(lighting.sun_direction.into());
+                    iface.refractive_index.update(refractive_index);
+                    iface.depth.update(*depth);
+                    let state = RenderState::default().set_blending((
+                        Equation::Additive,
+                        Factor::One,
+                        Factor::One,
+                    ));
+                    render_gate.render(state, |tess_gate| {
+                        iface.edges.update(0);
+                        tess_gate.render(context, tess.into());
+                        // Strips of the neighbouring tiles, whose light
+                        // can land within the margin of this one
+                        iface.edges.update(1);
+                        let slice = TessSlice::inst_whole(edge_tess, 4);
+                        tess_gate.render(context, slice);
+                    });
+                });
+            },
+        );
+    }
+}
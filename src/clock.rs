@@ -0,0 +1,159 @@
+/// Most steps taken in one frame. When the simulation can't keep up, the
+/// rest of the elapsed time is dropped instead of piling up.
+const MAX_STEPS_PER_FRAME: u32 = 8;
+
+/// Simulation time, kept apart from the wall clock. Elapsed frame time is
+/// scaled and cut into fixed steps, and the time is always a whole number of
+/// steps, so the same steps give the same results at any frame rate.
+pub struct OceanClock {
+    step: f32,
+    /// Simulated seconds per wall-clock second
+    pub time_scale: f32,
+    paused: bool,
+    steps: u64,
+    /// Scaled wall-clock time not yet taken as a step
+    accumulator: f32,
+}
+
+impl OceanClock {
+    /// A clock that advances in steps of `step` seconds.
+    pub fn new(step: f32) -> Self {
+        assert!(step > 0.0, "step must be positive");
+        Self {
+            step,
+            time_scale: 1.0,
+            paused: false,
+            steps: 0,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Length of one step, in simulated seconds.
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// Simulated seconds since time zero.
+    pub fn time(&self) -> f32 {
+        (self.steps as f64 * self.step as f64) as f32
+    }
+
+    /// Adds `wall_dt` seconds of wall-clock time and returns how many steps
+    /// the simulation should take to catch up. Nothing passes while paused.
+    pub fn advance(&mut self, wall_dt: f32) -> u32 {
+        if self.paused {
+            return 0;
+        }
+        self.accumulator += wall_dt * self.time_scale.max(0.0);
+        let steps = (self.accumulator / self.step).floor() as u32;
+        self.accumulator -= steps as f32 * self.step;
+        let steps = steps.min(MAX_STEPS_PER_FRAME);
+        self.steps += steps as u64;
+        steps
+    }
+
+    /// Jumps to `time` seconds, rounded to the nearest step.
+    pub fn seek(&mut self, time: f32) {
+        self.steps = (time.max(0.0) as f64 / self.step as f64).round() as u64;
+        self.accumulator = 0.0;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        if self.is_paused() {
+            self.resume();
+        } else {
+            self.pause();
+        }
+    }
+}
+
+impl Default for OceanClock {
+    fn default() -> Self {
+        Self::new(1.0 / 60.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_whole_steps_and_keeps_the_remainder() {
+        let mut clock = OceanClock::new(0.25);
+        assert_eq!(clock.advance(0.6), 2);
+        assert_eq!(clock.time(), 0.5);
+        // 0.1 left over, plus 0.2
+        assert_eq!(clock.advance(0.2), 1);
+        assert_eq!(clock.time(), 0.75);
+        assert_eq!(clock.advance(0.0), 0);
+    }
+
+    #[test]
+    fn gives_the_same_time_at_any_frame_rate() {
+        let mut slow = OceanClock::new(0.01);
+        let mut fast = OceanClock::new(0.01);
+        for _ in 0..30 {
+            slow.advance(1.0 / 30.0);
+        }
+        for _ in 0..120 {
+            fast.advance(1.0 / 120.0);
+        }
+        assert!((slow.time() - fast.time()).abs() <= 0.01);
+    }
+
+    #[test]
+    fn scales_time() {
+        let mut clock = OceanClock::new(0.5);
+        clock.time_scale = 2.0;
+        assert_eq!(clock.advance(1.0), 4);
+        clock.time_scale = -1.0;
+        assert_eq!(clock.advance(1.0), 0);
+        assert_eq!(clock.time(), 2.0);
+    }
+
+    #[test]
+    fn stands_still_while_paused() {
+        let mut clock = OceanClock::new(0.5);
+        clock.advance(1.0);
+        clock.pause();
+        assert!(clock.is_paused());
+        assert_eq!(clock.advance(10.0), 0);
+        assert_eq!(clock.time(), 1.0);
+        // Time spent paused isn't made up for afterwards
+        clock.toggle_pause();
+        assert!(!clock.is_paused());
+        assert_eq!(clock.advance(0.5), 1);
+        assert_eq!(clock.time(), 1.5);
+    }
+
+    #[test]
+    fn drops_time_it_cannot_catch_up_with() {
+        let mut clock = OceanClock::new(0.1);
+        assert_eq!(clock.advance(100.0), MAX_STEPS_PER_FRAME);
+        assert_eq!(clock.advance(0.0), 0);
+    }
+
+    #[test]
+    fn seeks_to_the_nearest_step() {
+        let mut clock = OceanClock::new(0.5);
+        clock.advance(0.4);
+        clock.seek(3.3);
+        assert_eq!(clock.time(), 3.5);
+        // The partial step from before the seek is gone
+        assert_eq!(clock.advance(0.2), 0);
+        clock.seek(-2.0);
+        assert_eq!(clock.time(), 0.0);
+    }
+}
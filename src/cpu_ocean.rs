@@ -0,0 +1,470 @@
+use crate::fft::{twiddle_pixels, uniform_noise, Spectrum, N};
+use crate::heightfield::Heightfield;
+use crate::surface::OceanSurface;
+use std::ops::{Add, Mul, Sub};
+
+const TAU: f32 = std::f32::consts::PI * 2.0;
+const G: f32 = 9.81;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Complex {
+    pub re: f32,
+    pub im: f32,
+}
+
+impl Complex {
+    pub fn new(re: f32, im: f32) -> Self {
+        Self { re, im }
+    }
+
+    pub fn conj(self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+
+    pub fn scale(self, factor: f32) -> Self {
+        Self::new(self.re * factor, self.im * factor)
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(
+            self.re * rhs.re - self.im * rhs.im,
+            self.re * rhs.im + self.im * rhs.re,
+        )
+    }
+}
+
+/// Runs the same H0k → Hkt → Fft → inversion pipeline as `Ocean`, on the CPU,
+/// for machines without a GPU. Each step follows the shader it replaces down
+/// to the texel offsets, so with the same `Spectrum` both backends agree on
+/// the heightfield up to floating point error.
+pub struct CpuOcean {
+    spectrum: Spectrum,
+    /// h0(k) and h0(-k) per texel, like the output of `h0k.frag`
+    h0k: Vec<(Complex, Complex)>,
+    /// Twiddle factor and input indices per line position and stage
+    twiddles: Vec<(Complex, usize, usize)>,
+    threads: usize,
+    heightfield: Heightfield,
+    /// Surface velocity along x, y and z
+    velocity: [Heightfield; 3],
+}
+
+impl CpuOcean {
+    pub fn new(spectrum: &Spectrum) -> Self {
+        let side = N as usize;
+        let threads = std::thread::available_parallelism()
+            .map(|threads| threads.get())
+            .unwrap_or(1);
+
+        let noise = uniform_noise(spectrum.seed);
+        let mut h0k = vec![Default::default(); side * side];
+        for_each_row(&mut h0k, threads, |y, row| {
+            for (x, texel) in row.iter_mut().enumerate() {
+                let random = noise[y * side + x];
+                *texel = initial_amplitudes(spectrum, x, y, random);
+            }
+        });
+
+        let twiddles = twiddle_pixels()
+            .into_iter()
+            .map(|(re, im, z, w)| {
+                (Complex::new(re, im), z as usize, w as usize)
+            })
+            .collect();
+
+        let mut ocean = Self {
+            spectrum: spectrum.clone(),
+            h0k,
+            twiddles,
+            threads,
+            heightfield: Heightfield::new(vec![0.0; side * side]),
+            velocity: [
+                Heightfield::new(vec![0.0; side * side]),
+                Heightfield::new(vec![0.0; side * side]),
+                Heightfield::new(vec![0.0; side * side]),
+            ],
+        };
+        ocean.simulate(0.0);
+        ocean
+    }
+
+    /// The equivalent of `Ocean::simulate`, for the CPU.
+    pub fn simulate(&mut self, time: f32) {
+        let side = N as usize;
+        let Self {
+            spectrum,
+            h0k,
+            threads,
+            ..
+        } = self;
+        let wave = |x: usize, y: usize| {
+            wave_at(spectrum, x, y, h0k[y * side + x], time)
+        };
+
+        // hkt.frag
+        let mut height_spectrum = vec![Complex::default(); side * side];
+        for_each_row(&mut height_spectrum, *threads, |y, row| {
+            for (x, texel) in row.iter_mut().enumerate() {
+                let wave = wave(x, y);
+                *texel = wave.forward + wave.backward;
+            }
+        });
+
+        let mut velocity_spectra = vec![Default::default(); side * side];
+        for_each_row(&mut velocity_spectra, *threads, |y, row| {
+            let hermitian = |a: Complex, b: Complex| (a + b.conj()).scale(0.5);
+            for (x, texel) in row.iter_mut().enumerate() {
+                let (mirror_x, mirror_y) =
+                    ((side - x) % side, (side - y) % side);
+                let [vx, vz, vy] = velocity_at(spectrum, wave(x, y));
+                let [mirror_vx, mirror_vz, mirror_vy] =
+                    velocity_at(spectrum, wave(mirror_x, mirror_y));
+                let vx = hermitian(vx, mirror_vx);
+                let vz = hermitian(vz, mirror_vz);
+                let vy = hermitian(vy, mirror_vy);
+                *texel = (vx + Complex::new(0.0, 1.0) * vz, vy);
+            }
+        });
+        let (horizontal_spectrum, vertical_spectrum): (Vec<_>, Vec<_>) =
+            velocity_spectra.into_iter().unzip();
+
+        let heights = self.inverse_transform(height_spectrum);
+        let horizontal = self.inverse_transform(horizontal_spectrum);
+        let vertical = self.inverse_transform(vertical_spectrum);
+
+        self.heightfield =
+            Heightfield::new(heights.iter().map(|h| h.re).collect());
+        self.velocity = [
+            Heightfield::new(horizontal.iter().map(|v| v.re).collect()),
+            Heightfield::new(vertical.iter().map(|v| v.re).collect()),
+            Heightfield::new(horizontal.iter().map(|v| v.im).collect()),
+        ];
+    }
+
+    pub fn heightfield(&self) -> &Heightfield {
+        &self.heightfield
+    }
+
+    /// The butterfly passes of `Fft::render_layers`, including the inversion
+    /// folded into the last one.
+    fn inverse_transform(&self, mut data: Vec<Complex>) -> Vec<Complex> {
+        let side = N as usize;
+
+        // butterfly.glsl, horizontal then vertical
+        self.transform_rows(&mut data);
+        let mut data = transpose(&data, side);
+        self.transform_rows(&mut data);
+        let data = transpose(&data, side);
+
+        let scale = 1.0 / (N * N) as f32;
+        data.iter()
+            .enumerate()
+            .map(|(i, &value)| {
+                let (x, y) = (i % side, i / side);
+                let perm = if (x + y) % 2 == 0 { 1.0 } else { -1.0 };
+                value.scale(perm * scale)
+            })
+            .collect()
+    }
+
+    /// Every butterfly stage along each row, in parallel.
+    fn transform_rows(&self, data: &mut [Complex]) {
+        let side = N as usize;
+        let bits = (N as f32).log2() as usize;
+        let twiddles = &self.twiddles;
+        for_each_row(data, self.threads, |_, row| {
+            let mut output = vec![Complex::default(); side];
+            for stage in 0..bits {
+                for (position, out) in output.iter_mut().enumerate() {
+                    let (w, p, q) = twiddles[position * bits + stage];
+                    *out = row[p] + w * row[q];
+                }
+                row.copy_from_slice(&output);
+            }
+        });
+    }
+}
+
+impl OceanSurface for CpuOcean {
+    fn height_at(&self, x: f32, z: f32) -> f32 {
+        self.heightfield.height_at(x, z)
+    }
+
+    fn surface_velocity_at(&self, x: f32, z: f32) -> glm::Vec3 {
+        let [vx, vy, vz] = &self.velocity;
+        glm::vec3(vx.height_at(x, z), vy.height_at(x, z), vz.height_at(x, z))
+    }
+
+    fn peak_wavenumber(&self) -> f32 {
+        self.spectrum.peak_wavenumber()
+    }
+}
+
+/// The wave vector `hkt.frag` uses for the texel at `x`, `y`.
+fn wave_vector(spectrum: &Spectrum, x: f32, y: f32) -> glm::Vec2 {
+    let half = N as f32 / 2.0;
+    glm::vec2(x - half, y - half) * TAU / spectrum.scale as f32
+}
+
+struct Wave {
+    k: glm::Vec2,
+    w: f32,
+    /// h0(k) e^(iwt)
+    forward: Complex,
+    /// conj(h0(-k)) e^(-iwt)
+    backward: Complex,
+}
+
+/// The two terms of h(k, t) at the texel at `x`, `y`, as in `hkt.frag`.
+fn wave_at(
+    spectrum: &Spectrum,
+    x: usize,
+    y: usize,
+    (h0, h0_minus): (Complex, Complex),
+    time: f32,
+) -> Wave {
+    let k = wave_vector(spectrum, x as f32, y as f32);
+    let magnitude = glm::length(&k).max(0.00001);
+    let w = (G * magnitude).sqrt();
+    let exp_iwt = Complex::new((w * time).cos(), (w * time).sin());
+    Wave {
+        k,
+        w,
+        forward: h0 * exp_iwt,
+        backward: h0_minus.conj() * exp_iwt.conj(),
+    }
+}
+
+/// Spectra of the surface velocity along x, z and y, as in `hkt.frag`.
+fn velocity_at(spectrum: &Spectrum, wave: Wave) -> [Complex; 3] {
+    let difference = wave.forward - wave.backward;
+    let direction = wave.k / glm::length(&wave.k).max(0.00001);
+    let to_world = N as f32 / spectrum.scale as f32;
+    [
+        difference.scale(-wave.w * direction.x * to_world),
+        difference.scale(-wave.w * direction.y * to_world),
+        Complex::new(0.0, wave.w) * difference,
+    ]
+}
+
+/// h0(k) and h0(-k) for the texel at `x`, `y`, as in `h0k.frag`.
+fn initial_amplitudes(
+    spectrum: &Spectrum,
+    x: usize,
+    y: usize,
+    random: (f32, f32, f32, f32),
+) -> (Complex, Complex) {
+    // h0k.frag doesn't subtract the half texel that hkt.frag does
+    let k = wave_vector(spectrum, x as f32 + 0.5, y as f32 + 0.5);
+
+    // Box-Muller-Method
+    let clamp = |r: f32| r.clamp(0.001, 1.0);
+    let (r, g, b, a) = random;
+    let (r, g, b, a) = (clamp(r), clamp(g), clamp(b), clamp(a));
+    let (a0, a1) = ((-2.0 * r.ln()).sqrt(), (-2.0 * g.ln()).sqrt());
+    let (b0, b1) = (TAU * b, TAU * a);
+
+    let h0 = phillips(spectrum, k);
+    let h0_minus = phillips(spectrum, -k);
+    (
+        Complex::new(a0 * b0.cos(), a0 * b0.sin()).scale(h0),
+        Complex::new(a1 * b1.cos(), a1 * b1.sin()).scale(h0_minus),
+    )
+}
+
+fn phillips(spectrum: &Spectrum, k: glm::Vec2) -> f32 {
+    let l_ = (spectrum.intensity * spectrum.intensity) / G;
+    let l = spectrum.l;
+
+    let mag = glm::length(&k).max(0.0001);
+    let mag_sq = mag * mag;
+    let alignment =
+        glm::dot(&glm::normalize(&k), &glm::normalize(&spectrum.direction));
+
+    let phillips_k = spectrum.amplitude / (mag_sq * mag_sq)
+        * alignment
+        * alignment
+        * (-1.0 / (mag_sq * l_ * l_)).exp()
+        * (-mag_sq * l * l).exp();
+
+    (phillips_k.sqrt() / 2f32.sqrt()).clamp(-4000.0, 4000.0)
+}
+
+fn transpose(data: &[Complex], side: usize) -> Vec<Complex> {
+    let mut transposed = vec![Complex::default(); side * side];
+    for y in 0..side {
+        for x in 0..side {
+            transposed[x * side + y] = data[y * side + x];
+        }
+    }
+    transposed
+}
+
+/// Calls `f` with the index and contents of each row of a square map,
+/// spreading contiguous runs of rows over `threads` threads.
+fn for_each_row<T, F>(data: &mut [T], threads: usize, f: F)
+where
+    T: Send,
+    F: Fn(usize, &mut [T]) + Sync,
+{
+    let side = N as usize;
+    let rows_per_thread = side.div_ceil(threads);
+    let f = &f;
+    std::thread::scope(|scope| {
+        for (chunk_index, chunk) in
+            data.chunks_mut(rows_per_thread * side).enumerate()
+        {
+            scope.spawn(move || {
+                for (i, row) in chunk.chunks_mut(side).enumerate() {
+                    f(chunk_index * rows_per_thread + i, row);
+                }
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Largest difference between `actual` and `expected`, relative to the
+    /// largest magnitude in `expected`.
+    fn relative_error(actual: &[f32], expected: &[f32]) -> f32 {
+        let largest = expected.iter().fold(0.0f32, |max, v| max.max(v.abs()));
+        let error = actual
+            .iter()
+            .zip(expected)
+            .fold(0.0f32, |max, (a, e)| max.max((a - e).abs()));
+        error / largest
+    }
+
+    #[test]
+    fn inverse_transform_matches_naive_dft() {
+        use rand::{Rng, SeedableRng};
+        let side = N as usize;
+        let ocean = CpuOcean::new(&Spectrum::default());
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let input: Vec<Complex> = (0..side * side)
+            .map(|_| Complex::new(rng.gen_range(-1.0, 1.0), 0.0))
+            .collect();
+
+        let actual = ocean.inverse_transform(input.clone());
+
+        // The same transform, one axis at a time, in double precision
+        let dft_rows = |data: &[(f64, f64)]| {
+            let mut output = vec![(0.0, 0.0); side * side];
+            for y in 0..side {
+                for x in 0..side {
+                    let mut sum = (0.0, 0.0);
+                    for k in 0..side {
+                        let (re, im) = data[y * side + k];
+                        let t = std::f64::consts::PI * 2.0 * (k * x) as f64
+                            / side as f64;
+                        sum.0 += re * t.cos() - im * t.sin();
+                        sum.1 += re * t.sin() + im * t.cos();
+                    }
+                    output[y * side + x] = sum;
+                }
+            }
+            output
+        };
+        let transpose = |data: &[(f64, f64)]| {
+            let mut transposed = vec![(0.0, 0.0); side * side];
+            for y in 0..side {
+                for x in 0..side {
+                    transposed[x * side + y] = data[y * side + x];
+                }
+            }
+            transposed
+        };
+        let data: Vec<_> =
+            input.iter().map(|c| (c.re as f64, c.im as f64)).collect();
+        let expected = transpose(&dft_rows(&transpose(&dft_rows(&data))));
+        let scale = 1.0 / (side * side) as f64;
+        let expected: Vec<_> = expected
+            .iter()
+            .enumerate()
+            .map(|(i, &(re, im))| {
+                let perm = (-1f64).powi((i % side + i / side) as i32);
+                (re * perm * scale, im * perm * scale)
+            })
+            .collect();
+
+        let real = |values: &[Complex]| -> Vec<f32> {
+            values.iter().map(|c| c.re).collect()
+        };
+        let imaginary = |values: &[Complex]| -> Vec<f32> {
+            values.iter().map(|c| c.im).collect()
+        };
+        let expected_real: Vec<_> =
+            expected.iter().map(|&(re, _)| re as f32).collect();
+        let expected_imaginary: Vec<_> =
+            expected.iter().map(|&(_, im)| im as f32).collect();
+        assert!(relative_error(&real(&actual), &expected_real) < 1e-4);
+        assert!(
+            relative_error(&imaginary(&actual), &expected_imaginary) < 1e-4
+        );
+    }
+
+    #[test]
+    fn simulate_depends_only_on_time() {
+        let mut ocean = CpuOcean::new(&Spectrum::default());
+        let (low, high) = ocean.heightfield().height_range();
+        assert!(low < 0.0 && high > 0.0);
+
+        ocean.simulate(3.0);
+        let first = ocean.heightfield().heights().to_vec();
+        ocean.simulate(1.0);
+        ocean.simulate(3.0);
+        assert_eq!(ocean.heightfield().heights(), &first[..]);
+    }
+
+    #[test]
+    #[ignore = "needs a display with OpenGL"]
+    fn matches_gpu_ocean() {
+        use luminance::context::GraphicsContext;
+        let sdl = sdl2::init().unwrap();
+        let video_system = sdl.video().unwrap();
+        let window = video_system
+            .window("cpu_ocean", 1, 1)
+            .opengl()
+            .hidden()
+            .build()
+            .unwrap();
+        let context = &mut crate::SdlContext::new(&video_system, window);
+
+        let spectrum = Spectrum::default();
+        let mut gpu = crate::ocean::Ocean::new(context, &spectrum);
+        let mut cpu = CpuOcean::new(&spectrum);
+        for &time in &[0.0, 2.5, 40.0] {
+            {
+                let builder = context.pipeline_builder();
+                let lighting = Default::default();
+                gpu.simulate(context, &builder, time, &lighting, false);
+            }
+            cpu.simulate(time);
+            let error = relative_error(
+                &gpu.read_heightmap(),
+                cpu.heightfield().heights(),
+            );
+            assert!(error < 1e-3, "{} apart at time {}", error, time);
+        }
+    }
+}
@@ -0,0 +1,469 @@
+use crate::sky::{face_direction, EnvironmentMap, FACES};
+use luminance::{
+    context::GraphicsContext,
+    framebuffer::Framebuffer,
+    pixel::R32F,
+    texture::{Dim2, Flat, GenMipmaps, MinFilter, Sampler, Texture},
+};
+use std::fmt;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+/// Side of the sharpest face of a prefiltered environment
+const FACE_SIZE: u32 = 128;
+/// Samples of the reflection lobe per texel of a rough level
+const SAMPLES: u32 = 32;
+
+#[derive(Debug)]
+pub enum HdrError {
+    Io(std::io::Error),
+    Format(&'static str),
+}
+
+impl fmt::Display for HdrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HdrError::Io(error) => write!(f, "could not read image: {}", error),
+            HdrError::Format(reason) => {
+                write!(f, "not a usable .hdr: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HdrError {}
+
+impl From<std::io::Error> for HdrError {
+    fn from(error: std::io::Error) -> Self {
+        HdrError::Io(error)
+    }
+}
+
+/// A high dynamic range image in linear RGB, such as a photographed sky in
+/// equirectangular projection.
+#[derive(Clone)]
+pub struct HdrImage {
+    width: usize,
+    height: usize,
+    pixels: Vec<glm::Vec3>,
+}
+
+impl HdrImage {
+    /// Reads a Radiance RGBE (.hdr) file. OpenEXR isn't supported.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, HdrError> {
+        let file = std::fs::File::open(path)?;
+        Self::read(BufReader::new(file))
+    }
+
+    pub fn read(mut reader: impl BufRead) -> Result<Self, HdrError> {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        if !line.starts_with("#?") {
+            return Err(HdrError::Format("missing #? signature"));
+        }
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(HdrError::Format("header never ends"));
+            }
+            let line = line.trim();
+            if line.is_empty() {
+                break;
+            }
+            if line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe" {
+                return Err(HdrError::Format("only RGBE pixels are supported"));
+            }
+        }
+
+        line.clear();
+        reader.read_line(&mut line)?;
+        let fields: Vec<_> = line.split_whitespace().collect();
+        let (height, width) = match fields.as_slice() {
+            ["-Y", height, "+X", width] => (height.parse(), width.parse()),
+            _ => return Err(HdrError::Format("unsupported orientation")),
+        };
+        let (height, width): (usize, usize) = match (height, width) {
+            (Ok(height), Ok(width)) if height > 0 && width > 0 => {
+                (height, width)
+            }
+            _ => return Err(HdrError::Format("bad resolution")),
+        };
+
+        let mut pixels = Vec::with_capacity(width * height);
+        let mut scanline = vec![[0u8; 4]; width];
+        for _ in 0..height {
+            read_scanline(&mut reader, &mut scanline)?;
+            pixels.extend(scanline.iter().map(|&rgbe| decode(rgbe)));
+        }
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Half the size, each pixel the average of four.
+    fn downsample(&self) -> Self {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = glm::Vec3::zeros();
+                for (dx, dy) in &[(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let sx = (x * 2 + dx).min(self.width - 1);
+                    let sy = (y * 2 + dy).min(self.height - 1);
+                    sum += self.pixels[sy * self.width + sx];
+                }
+                pixels.push(sum / 4.0);
+            }
+        }
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// The pixel in `direction`, reading the image as equirectangular with
+    /// straight up at the top row and -z at the center.
+    fn sample(&self, direction: glm::Vec3) -> glm::Vec3 {
+        use std::f32::consts::PI;
+        let u = direction.x.atan2(-direction.z) / (2.0 * PI) + 0.5;
+        let v = direction.y.clamp(-1.0, 1.0).acos() / PI;
+        let x = (u * self.width as f32 - 0.5).rem_euclid(self.width as f32);
+        let y =
+            (v * self.height as f32 - 0.5).clamp(0.0, (self.height - 1) as f32);
+
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1) % self.width, (y0 + 1).min(self.height - 1));
+        let (fx, fy) = (x.fract(), y.fract());
+        let at = |x: usize, y: usize| self.pixels[y * self.width + x];
+        let top = glm::lerp(&at(x0, y0), &at(x1, y0), fx);
+        let bottom = glm::lerp(&at(x0, y1), &at(x1, y1), fx);
+        glm::lerp(&top, &bottom, fy)
+    }
+}
+
+fn read_scanline(
+    reader: &mut impl Read,
+    scanline: &mut [[u8; 4]],
+) -> Result<(), HdrError> {
+    let width = scanline.len();
+    let mut start = [0u8; 4];
+    reader.read_exact(&mut start)?;
+    let run_length_encoded = (8..0x8000).contains(&width)
+        && start[0] == 2
+        && start[1] == 2
+        && ((start[2] as usize) << 8 | start[3] as usize) == width;
+
+    if !run_length_encoded {
+        scanline[0] = start;
+        for pixel in &mut scanline[1..] {
+            reader.read_exact(pixel)?;
+        }
+        return Ok(());
+    }
+
+    // Each channel on its own, as runs of one repeated byte or of literals
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let mut count = [0u8; 1];
+            reader.read_exact(&mut count)?;
+            let (count, repeat) = match count[0] {
+                count if count > 128 => (count as usize - 128, true),
+                count => (count as usize, false),
+            };
+            if count == 0 || x + count > width {
+                return Err(HdrError::Format("bad run length"));
+            }
+            let run = &mut scanline[x..x + count];
+            if repeat {
+                let mut byte = [0u8; 1];
+                reader.read_exact(&mut byte)?;
+                for pixel in run {
+                    pixel[channel] = byte[0];
+                }
+            } else {
+                let mut bytes = vec![0u8; count];
+                reader.read_exact(&mut bytes)?;
+                for (pixel, &byte) in run.iter_mut().zip(&bytes) {
+                    pixel[channel] = byte;
+                }
+            }
+            x += count;
+        }
+    }
+    Ok(())
+}
+
+fn decode([r, g, b, e]: [u8; 4]) -> glm::Vec3 {
+    if e == 0 {
+        return glm::Vec3::zeros();
+    }
+    let scale = 2f32.powi(e as i32 - (128 + 8));
+    glm::vec3(r as f32, g as f32, b as f32) * scale
+}
+
+/// Turns an equirectangular `image` into a cubemap for reflections and
+/// backgrounds. Mip level 0 is the image itself, as a mirror reflects it, and
+/// each level after it is blurred for a rougher surface, up to a roughness of
+/// one at the last. `ocean.frag` picks the level from its material's
+/// roughness.
+pub fn prefilter(
+    context: &mut impl GraphicsContext,
+    image: &HdrImage,
+) -> EnvironmentMap {
+    let levels = (FACE_SIZE as f32).log2() as u32 + 1;
+    let mut sampler = Sampler::default();
+    sampler.min_filter = MinFilter::LinearMipmapLinear;
+    let cubemap =
+        Texture::new(context, FACE_SIZE, levels as usize - 1, &sampler)
+            .expect("cubemap creation");
+
+    let mut pyramid = vec![image.clone()];
+    while pyramid.last().unwrap().width > 8 {
+        let smaller = pyramid.last().unwrap().downsample();
+        pyramid.push(smaller);
+    }
+
+    let filtered: Vec<_> = (0..levels)
+        .map(|level| {
+            let roughness = level as f32 / (levels - 1) as f32;
+            filter_level(&pyramid, FACE_SIZE >> level, roughness)
+        })
+        .collect();
+
+    for (face, texels) in FACES.iter().zip(&filtered[0]) {
+        cubemap.upload_part(GenMipmaps::No, ([0, 0], *face), FACE_SIZE, texels);
+    }
+
+    upload_mip_levels(context, &cubemap, &filtered[1..]);
+
+    cubemap
+}
+
+/// Puts `levels` into mip levels 1 and up of `cubemap`, each level with its
+/// faces in the order of `FACES`. luminance only uploads the base level, so
+/// this goes through GL while a pipeline on a throwaway framebuffer has the
+/// cubemap bound, and puts back the unpack state it changes.
+fn upload_mip_levels(
+    context: &mut impl GraphicsContext,
+    cubemap: &EnvironmentMap,
+    levels: &[Vec<Vec<(f32, f32, f32)>>],
+) {
+    let target: Framebuffer<Flat, Dim2, R32F, ()> =
+        Framebuffer::new(context, [1, 1], 0).expect("framebuffer creation");
+    let builder = context.pipeline_builder();
+    builder.pipeline(&target, [0.0, 0.0, 0.0, 0.0], |pipeline, _| {
+        // Binding the cubemap leaves it on the active texture unit
+        let _bound = pipeline.bind_texture(cubemap);
+        unsafe {
+            let mut unpack_buffer = 0;
+            gl::GetIntegerv(
+                gl::PIXEL_UNPACK_BUFFER_BINDING,
+                &mut unpack_buffer,
+            );
+            let mut alignment = 0;
+            gl::GetIntegerv(gl::UNPACK_ALIGNMENT, &mut alignment);
+            gl::BindBuffer(gl::PIXEL_UNPACK_BUFFER, 0);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+
+            for (level, faces) in levels.iter().enumerate() {
+                let level = level + 1;
+                let size = (FACE_SIZE >> level) as i32;
+                for (index, texels) in faces.iter().enumerate() {
+                    gl::TexSubImage2D(
+                        gl::TEXTURE_CUBE_MAP_POSITIVE_X + index as u32,
+                        level as i32,
+                        0,
+                        0,
+                        size,
+                        size,
+                        gl::RGB,
+                        gl::FLOAT,
+                        texels.as_ptr() as *const _,
+                    );
+                }
+            }
+
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, alignment);
+            gl::BindBuffer(gl::PIXEL_UNPACK_BUFFER, unpack_buffer as u32);
+        }
+    });
+}
+
+/// Every face of one level of the cubemap, in the order of `FACES`.
+fn filter_level(
+    pyramid: &[HdrImage],
+    size: u32,
+    roughness: f32,
+) -> Vec<Vec<(f32, f32, f32)>> {
+    use std::f32::consts::PI;
+    let base = &pyramid[0];
+    let pixel_solid_angle = 2.0 * PI * PI / (base.width * base.height) as f32;
+    // Picks the level of the pyramid whose pixels cover `solid_angle`
+    let sample = |direction: glm::Vec3, solid_angle: f32| {
+        let level = 0.5 * (solid_angle / pixel_solid_angle).log2();
+        let level = level.round().clamp(0.0, (pyramid.len() - 1) as f32);
+        pyramid[level as usize].sample(direction)
+    };
+    let texel_solid_angle = 4.0 * PI / (6 * size * size) as f32;
+
+    let filter = |normal: glm::Vec3| {
+        if roughness == 0.0 {
+            return sample(normal, texel_solid_angle);
+        }
+
+        // GGX importance sampling, taking the view to be along the normal
+        let alpha = roughness * roughness;
+        let alpha2 = alpha * alpha;
+        let up = if normal.y.abs() < 0.999 {
+            glm::vec3(0.0, 1.0, 0.0)
+        } else {
+            glm::vec3(1.0, 0.0, 0.0)
+        };
+        let tangent = glm::normalize(&up.cross(&normal));
+        let bitangent = normal.cross(&tangent);
+
+        let mut sum = glm::Vec3::zeros();
+        let mut weight = 0.0;
+        for i in 0..SAMPLES {
+            let u = i as f32 / SAMPLES as f32;
+            let v = i.reverse_bits() as f32 / 2f32.powi(32);
+            let cos_theta = ((1.0 - v) / (1.0 + (alpha2 - 1.0) * v)).sqrt();
+            let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+            let phi = 2.0 * PI * u;
+            let half = tangent * (sin_theta * phi.cos())
+                + bitangent * (sin_theta * phi.sin())
+                + normal * cos_theta;
+            let light = half * (2.0 * normal.dot(&half)) - normal;
+            let n_l = normal.dot(&light);
+            if n_l <= 0.0 {
+                continue;
+            }
+
+            let d = cos_theta * cos_theta * (alpha2 - 1.0) + 1.0;
+            let distribution = alpha2 / (PI * d * d);
+            let pdf = distribution / 4.0;
+            let solid_angle = 1.0 / (SAMPLES as f32 * pdf);
+            sum += sample(light, solid_angle) * n_l;
+            weight += n_l;
+        }
+        sum / weight.max(0.0001)
+    };
+
+    let filter = &filter;
+    std::thread::scope(|scope| {
+        let faces: Vec<_> = FACES
+            .iter()
+            .map(|&face| {
+                scope.spawn(move || {
+                    let mut texels = Vec::with_capacity((size * size) as usize);
+                    for y in 0..size {
+                        for x in 0..size {
+                            let color =
+                                filter(face_direction(face, x, y, size));
+                            texels.push((color.x, color.y, color.z));
+                        }
+                    }
+                    texels
+                })
+            })
+            .collect();
+        faces.into_iter().map(|face| face.join().unwrap()).collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(width: usize, height: usize) -> Vec<u8> {
+        let mut bytes = b"#?RADIANCE\n# made by hand\n".to_vec();
+        bytes.extend_from_slice(b"FORMAT=32-bit_rle_rgbe\n\n");
+        bytes.extend(format!("-Y {} +X {}\n", height, width).bytes());
+        bytes
+    }
+
+    #[test]
+    fn decodes_rgbe() {
+        assert_eq!(decode([128, 64, 0, 129]), glm::vec3(1.0, 0.5, 0.0));
+        assert_eq!(
+            decode([128, 128, 128, 136]),
+            glm::vec3(128.0, 128.0, 128.0)
+        );
+        assert_eq!(decode([200, 10, 10, 0]), glm::Vec3::zeros());
+    }
+
+    #[test]
+    fn reads_flat_scanlines() {
+        let mut bytes = header(2, 2);
+        for pixel in &[
+            [128, 0, 0, 129],
+            [0, 128, 0, 129],
+            [0, 0, 128, 130],
+            [0, 0, 0, 0],
+        ] {
+            bytes.extend_from_slice(pixel);
+        }
+        let image = HdrImage::read(&bytes[..]).unwrap();
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(
+            image.pixels,
+            vec![
+                glm::vec3(1.0, 0.0, 0.0),
+                glm::vec3(0.0, 1.0, 0.0),
+                glm::vec3(0.0, 0.0, 2.0),
+                glm::Vec3::zeros(),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_run_length_encoded_scanlines() {
+        let width = 10;
+        let mut bytes = header(width, 1);
+        bytes.extend_from_slice(&[2, 2, 0, width as u8]);
+        // Red: a run of six, then four literals
+        bytes.extend_from_slice(&[128 + 6, 64, 4, 1, 2, 3, 4]);
+        // Green and blue: one run each
+        bytes.extend_from_slice(&[128 + 10, 0]);
+        bytes.extend_from_slice(&[128 + 10, 32]);
+        // Exponent: two runs
+        bytes.extend_from_slice(&[128 + 5, 129, 128 + 5, 130]);
+
+        let image = HdrImage::read(&bytes[..]).unwrap();
+        // A scale of 1/128 for the first five pixels, 1/64 for the rest
+        let red: Vec<_> = image.pixels.iter().map(|p| p.x * 64.0).collect();
+        assert_eq!(
+            red,
+            [32.0, 32.0, 32.0, 32.0, 32.0, 64.0, 1.0, 2.0, 3.0, 4.0]
+        );
+        assert_eq!(image.pixels[0].z, 0.25);
+        assert_eq!(image.pixels[9].z, 0.5);
+        assert!(image.pixels.iter().all(|p| p.y == 0.0));
+    }
+
+    #[test]
+    fn rejects_broken_files() {
+        let fails = |bytes: &[u8]| HdrImage::read(bytes).is_err();
+        assert!(fails(b"P6\n2 2\n255\n"));
+        assert!(fails(
+            b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n\0\0\0\0"
+        ));
+        assert!(fails(b"#?RADIANCE\n\n+Y 1 +X 1\n\0\0\0\0"));
+        // Cut off in the middle of the pixels
+        let mut bytes = header(2, 2);
+        bytes.extend_from_slice(&[1, 2, 3, 4]);
+        assert!(fails(&bytes));
+        // A run reaching past the end of the scanline
+        let mut bytes = header(8, 1);
+        bytes.extend_from_slice(&[2, 2, 0, 8, 128 + 9, 0]);
+        assert!(fails(&bytes));
+        // No pixels at all
+        assert!(fails(&header(0, 4)));
+        assert!(fails(&header(4, 0)));
+    }
+}
@@ -25,22 +25,69 @@ struct H0kInterface {
 
 type H0kTexture = Texture<Flat, Dim2, RGBA32F>;
 
+/// Parameters of the Phillips spectrum the ocean is generated from.
+#[derive(Clone, Debug)]
+pub struct Spectrum {
+    pub seed: u64,
+    pub scale: i32,
+    pub amplitude: f32,
+    pub intensity: f32, // wind speed
+    pub direction: glm::Vec2,
+    pub l: f32, // capillary supress factor
+}
+
+impl Spectrum {
+    /// Wavenumber where the spectrum peaks, in radians per world unit. The
+    /// spectrum's `scale` is drawn across N world units.
+    pub fn peak_wavenumber(&self) -> f32 {
+        let l = self.intensity * self.intensity / 9.81;
+        let k = 1.0 / (l * 2f32.sqrt());
+        k * self.scale as f32 / N as f32
+    }
+}
+
+impl Default for Spectrum {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            scale: 1000,
+            amplitude: 4.0,
+            intensity: 40.0,
+            direction: glm::vec2(1.0, 1.0),
+            l: 0.5,
+        }
+    }
+}
+
 pub struct H0k {
     tess: Tess,
     input_texture: Texture<Flat, Dim2, RGBA32F>,
     shader: Program<(), (), H0kInterface>,
     pub framebuffer: Framebuffer<Flat, Dim2, RGBA32F, ()>,
-    scale: i32,
-    amplitude: f32,
-    intensity: f32, // wind speed
-    direction: glm::Vec2,
-    l: f32, // capillary supress factor
+    spectrum: Spectrum,
 }
 
-const N: u32 = 0x100;
+pub const N: u32 = 0x100;
+
+/// The uniform random numbers `h0k.frag` turns into gaussian noise, one
+/// texel per frequency. The same seed always gives the same noise, so other
+/// backends can reproduce the spectrum.
+pub fn uniform_noise(seed: u64) -> Vec<(f32, f32, f32, f32)> {
+    use rand::{Rng, SeedableRng};
+    let length = N * N;
+    let mut pixels = Vec::with_capacity(length as usize);
+    let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+    for _ in 0..length {
+        pixels.push(rng.gen());
+    }
+    pixels
+}
 
 impl H0k {
-    pub fn new(context: &mut impl GraphicsContext) -> Self {
+    pub fn new(
+        context: &mut impl GraphicsContext,
+        spectrum: &Spectrum,
+    ) -> Self {
         let size = [N, N];
         let framebuffer =
             Framebuffer::new(context, size, 0).expect("framebuffer creation");
@@ -55,17 +102,7 @@ impl H0k {
         sampler.min_filter = MinFilter::Nearest;
 
         let input_texture = Texture::new(context, size, 0, &sampler).unwrap();
-        {
-            let length = N * N;
-            let mut pixels = Vec::with_capacity(length as usize);
-            let mut rng = rand::thread_rng();
-            for _ in 0..length {
-                use rand::Rng;
-                pixels.push(rng.gen());
-            }
-
-            input_texture.upload(GenMipmaps::No, &pixels);
-        }
+        input_texture.upload(GenMipmaps::No, &uniform_noise(spectrum.seed));
 
         let tess = TessBuilder::new(context)
             .set_mode(Mode::TriangleStrip)
@@ -78,14 +115,14 @@ impl H0k {
             input_texture,
             shader,
             framebuffer,
-            scale: 1000,
-            amplitude: 4.0,
-            intensity: 40.0, // wind speed
-            direction: glm::vec2(1.0, 1.0),
-            l: 0.5, // capillary supress factor
+            spectrum: spectrum.clone(),
         }
     }
 
+    pub fn spectrum(&self) -> &Spectrum {
+        &self.spectrum
+    }
+
     pub fn render(
         &self,
         context: &mut impl GraphicsContext,
@@ -95,15 +132,16 @@ impl H0k {
             &self.framebuffer,
             [1.0, 1.0, 0.0, 1.0],
             |pipeline, shader_gate| {
+                let spectrum = &self.spectrum;
                 let bound_noise = pipeline.bind_texture(&self.input_texture);
                 shader_gate.shade(&self.shader, |render_gate, iface| {
                     iface.input_texture.update(&bound_noise);
                     iface.n.update(N as i32);
-                    iface.scale.update(self.scale);
-                    iface.amplitude.update(self.amplitude);
-                    iface.intensity.update(self.intensity);
-                    iface.direction.update(self.direction.into());
-                    iface.l.update(self.l);
+                    iface.scale.update(spectrum.scale);
+                    iface.amplitude.update(spectrum.amplitude);
+                    iface.intensity.update(spectrum.intensity);
+                    iface.direction.update(spectrum.direction.into());
+                    iface.l.update(spectrum.l);
                     use luminance::render_state::RenderState;
                     render_gate.render(RenderState::default(), |tess_gate| {
                         tess_gate.render(context, (&self.tess).into());
@@ -120,19 +158,30 @@ struct HktInterface {
     input_texture:
         Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
     n: Uniform<i32>,
+    scale: Uniform<i32>,
     time: Uniform<f32>,
 }
 
 type HktTexture = Texture<Flat, Dim2, RGBA32F>;
 
+/// Evolves the spectrum to a point in time. The first color slot holds the
+/// height spectrum. The second holds the surface velocity spectra, packed so
+/// that after the inverse transform red, green and blue are the velocity
+/// along x, z and y, and alpha the vertical acceleration. The third holds
+/// what breaking detection needs: the derivatives of the horizontal
+/// displacement along x and x, z and z, and x and z, in red, green and blue.
 pub struct Hkt {
     tess: Tess,
     shader: Program<(), (), HktInterface>,
-    pub framebuffer: Framebuffer<Flat, Dim2, RGBA32F, ()>,
+    pub framebuffer: Framebuffer<Flat, Dim2, (RGBA32F, RGBA32F, RGBA32F), ()>,
+    scale: i32,
 }
 
 impl Hkt {
-    pub fn new(context: &mut impl GraphicsContext) -> Self {
+    pub fn new(
+        context: &mut impl GraphicsContext,
+        spectrum: &Spectrum,
+    ) -> Self {
         let size = [N, N];
         let framebuffer =
             Framebuffer::new(context, size, 0).expect("framebuffer creation");
@@ -156,6 +205,7 @@ impl Hkt {
             tess,
             shader,
             framebuffer,
+            scale: spectrum.scale,
         }
     }
 
@@ -165,7 +215,7 @@ impl Hkt {
         builder: &Builder,
         time: f32,
         input_texture: &H0kTexture,
-    ) -> &HktTexture {
+    ) -> &(HktTexture, HktTexture, HktTexture) {
         builder.pipeline(
             &self.framebuffer,
             [0.0, 0.0, 0.0, 1.0],
@@ -174,6 +224,7 @@ impl Hkt {
                 shader_gate.shade(&self.shader, |render_gate, iface| {
                     iface.input_texture.update(&bound_noise);
                     iface.n.update(N as i32);
+                    iface.scale.update(self.scale);
                     iface.time.update(time);
                     use luminance::render_state::RenderState;
                     render_gate.render(RenderState::default(), |tess_gate| {
@@ -188,52 +239,59 @@ impl Hkt {
 
 type TwiddleTexture = Texture<Flat, Dim2, RGBA32F>;
 
-pub fn twiddle_indices(context: &mut impl GraphicsContext) -> TwiddleTexture {
-    use luminance::texture::{MagFilter, MinFilter, Sampler};
-    let mut sampler = Sampler::default();
-    sampler.mag_filter = MagFilter::Nearest;
-    sampler.min_filter = MinFilter::Nearest;
+/// Per butterfly stage (x) and line position (y): the twiddle factor in
+/// (x, y) and the indices of the two inputs in (z, w). The first stage also
+/// bit-reverses its indices.
+pub fn twiddle_pixels() -> Vec<(f32, f32, f32, f32)> {
+    const TAU: f32 = std::f32::consts::PI * 2.0;
 
     let bits = (N as f32).log2() as u32;
     let width = bits;
     let height = N;
-    let texture = Texture::new(context, [width, height], 0, &sampler).unwrap();
-    {
-        const TAU: f32 = std::f32::consts::PI * 2.0;
+    let length = width * height;
+    let mut pixels = Vec::with_capacity(length as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let nf = N as f32;
+            let span = u32::pow(2, x);
 
-        let length = width * height;
-        let mut pixels = Vec::with_capacity(length as usize);
-        for y in 0..height {
-            for x in 0..width {
-                let nf = N as f32;
-                let span = u32::pow(2, x);
+            let index = span * 2;
 
-                let index = span * 2;
+            let k = (y as f32 * nf / index as f32) % nf;
+            let t = TAU * k / nf;
 
-                let k = (y as f32 * nf / index as f32) % nf;
-                let t = TAU * k / nf;
+            let top_wing = y % index < span;
 
-                let top_wing = y % index < span;
+            let reverse = |i: u32| i.reverse_bits().rotate_left(bits);
 
-                let reverse = |i: u32| i.reverse_bits().rotate_left(bits);
+            let (mut z, mut w) = if top_wing {
+                (y, y + span)
+            } else {
+                (y - span, y)
+            };
 
-                let (mut z, mut w) = if top_wing {
-                    (y, y + span)
-                } else {
-                    (y - span, y)
-                };
-
-                if x == 0 {
-                    z = reverse(z);
-                    w = reverse(w);
-                }
-
-                pixels.push((t.cos(), t.sin(), z as f32, w as f32));
+            if x == 0 {
+                z = reverse(z);
+                w = reverse(w);
             }
-        }
 
-        texture.upload(GenMipmaps::No, &pixels);
+            pixels.push((t.cos(), t.sin(), z as f32, w as f32));
+        }
     }
+    pixels
+}
+
+pub fn twiddle_indices(context: &mut impl GraphicsContext) -> TwiddleTexture {
+    use luminance::texture::{MagFilter, MinFilter, Sampler};
+    let mut sampler = Sampler::default();
+    sampler.mag_filter = MagFilter::Nearest;
+    sampler.min_filter = MinFilter::Nearest;
+
+    let bits = (N as f32).log2() as u32;
+    let width = bits;
+    let height = N;
+    let texture = Texture::new(context, [width, height], 0, &sampler).unwrap();
+    texture.upload(GenMipmaps::No, &twiddle_pixels());
 
     texture
 }
@@ -246,22 +304,47 @@ struct ButterflyInterface {
         Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
     stage: Uniform<i32>,
     direction: Uniform<i32>,
+    forward: Uniform<i32>,
+    invert: Uniform<i32>,
+    n: Uniform<i32>,
 }
 
 #[derive(UniformInterface)]
-struct InversionInterface {
-    input_texture:
+struct LayeredButterflyInterface {
+    twiddle_indices:
         Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    layer0: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    layer1: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    layer2: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    stage: Uniform<i32>,
+    direction: Uniform<i32>,
+    forward: Uniform<i32>,
+    invert: Uniform<i32>,
+    n: Uniform<i32>,
+}
+
+#[derive(UniformInterface)]
+struct MultiplyInterface {
+    lhs: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    rhs: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    n: Uniform<i32>,
 }
 
-type FftTexture = Texture<Flat, Dim2, RGBA32F>;
+pub type FftTexture = Texture<Flat, Dim2, RGBA32F>;
 pub type FftFramebuffer = Framebuffer<Flat, Dim2, RGBA32F, ()>;
+/// Three same-sized textures transformed together, one per color slot
+pub type FftLayers = (FftTexture, FftTexture, FftTexture);
+pub type LayeredFftFramebuffer =
+    Framebuffer<Flat, Dim2, (RGBA32F, RGBA32F, RGBA32F), ()>;
 
 pub struct Fft {
     twiddle_indices: TwiddleTexture,
     butterfly_shader: Program<(), (), ButterflyInterface>,
-    inversion_shader: Program<(), (), InversionInterface>,
+    layered_butterfly_shader: Program<(), (), LayeredButterflyInterface>,
+    multiply_shader: Program<(), (), MultiplyInterface>,
     pingpong_buffer: FftFramebuffer,
+    layered_pingpong_buffer: LayeredFftFramebuffer,
+    spectrum_buffers: [FftFramebuffer; 2],
     tess: Tess,
 }
 
@@ -271,12 +354,23 @@ impl Fft {
 
         let butterfly_shader = crate::shader::from_strings(
             QUAD_VS_SRC,
-            include_str!("../shaders/butterfly.frag"),
+            concat!(
+                include_str!("../shaders/butterfly.glsl"),
+                include_str!("../shaders/butterfly.frag"),
+            ),
         );
 
-        let inversion_shader = crate::shader::from_strings(
+        let layered_butterfly_shader = crate::shader::from_strings(
             QUAD_VS_SRC,
-            include_str!("../shaders/inversion.frag"),
+            concat!(
+                include_str!("../shaders/butterfly.glsl"),
+                include_str!("../shaders/layered-butterfly.frag"),
+            ),
+        );
+
+        let multiply_shader = crate::shader::from_strings(
+            QUAD_VS_SRC,
+            include_str!("../shaders/multiply.frag"),
         );
 
         let size = [N, N];
@@ -284,6 +378,14 @@ impl Fft {
         let pingpong_buffer =
             Framebuffer::new(context, size, 0).expect("framebuffer creation");
 
+        let layered_pingpong_buffer =
+            Framebuffer::new(context, size, 0).expect("framebuffer creation");
+
+        let spectrum_buffers = [
+            Framebuffer::new(context, size, 0).expect("framebuffer creation"),
+            Framebuffer::new(context, size, 0).expect("framebuffer creation"),
+        ];
+
         let tess = TessBuilder::new(context)
             .set_mode(Mode::TriangleStrip)
             .set_vertex_nb(4)
@@ -294,81 +396,60 @@ impl Fft {
             tess,
             twiddle_indices,
             butterfly_shader,
-            inversion_shader,
+            layered_butterfly_shader,
+            multiply_shader,
             pingpong_buffer,
+            layered_pingpong_buffer,
+            spectrum_buffers,
         }
     }
 
-    pub fn render<'a>(
+    /// Runs every butterfly stage over `input_texture`, alternating between
+    /// the two buffers. The first stage writes to `buffers[0]` and the
+    /// result ends up in `buffers[1]`. The input may be the color slot of
+    /// `buffers[1]`, since it is only read by the first stage.
+    ///
+    /// With `invert` set, the last stage also applies the sign flip and
+    /// scaling that turn the ocean spectrum into a real heightmap. Otherwise
+    /// the result is the raw, unnormalized complex transform.
+    fn butterflies(
         &self,
         context: &mut impl GraphicsContext,
         builder: &Builder,
         input_texture: &FftTexture,
-        output_buffer: &'a mut FftFramebuffer,
-    ) -> &'a FftTexture {
+        buffers: [&FftFramebuffer; 2],
+        forward: bool,
+        invert: bool,
+    ) {
         let Self {
             tess,
-            pingpong_buffer,
             twiddle_indices,
             butterfly_shader,
-            inversion_shader,
+            ..
         } = self;
 
-        let bits = (N as f32).log2() as usize;
-        let buffers = [pingpong_buffer, output_buffer];
-        let mut pingpong = bits % 2;
-        let mut first_round = true;
-
-        for &direction in &[0, 1] {
-            for stage in 0..bits {
-                let input = if first_round {
-                    first_round = false;
-                    input_texture
-                } else {
-                    buffers[pingpong].color_slot()
-                };
-                let output = buffers[1 - pingpong];
-
-                builder.pipeline(
-                    output,
-                    [1.0, 1.0, 0.0, 1.0],
-                    |pipeline, shader_gate| {
-                        let bound_twiddle =
-                            pipeline.bind_texture(twiddle_indices);
-                        let bound_input = pipeline.bind_texture(input);
-                        shader_gate.shade(
-                            butterfly_shader,
-                            |render_gate, iface| {
-                                iface.twiddle_indices.update(&bound_twiddle);
-                                iface.input_texture.update(&bound_input);
-                                iface.stage.update(stage as i32);
-                                iface.direction.update(direction);
-                                use luminance::render_state::RenderState;
-                                render_gate.render(
-                                    RenderState::default(),
-                                    |tess_gate| {
-                                        tess_gate.render(context, tess.into());
-                                    },
-                                );
-                            },
-                        );
-                    },
-                );
-                pingpong = 1 - pingpong;
-            }
-        }
-        {
-            let input = buffers[pingpong].color_slot();
-            let output = buffers[1 - pingpong];
+        for_each_stage(|stage| {
+            let input = match stage.input {
+                Some(buffer) => buffers[buffer].color_slot(),
+                None => input_texture,
+            };
+            let invert = invert && stage.last;
             builder.pipeline(
-                output,
+                buffers[stage.output],
                 [1.0, 1.0, 0.0, 1.0],
                 |pipeline, shader_gate| {
+                    let bound_twiddle = pipeline.bind_texture(twiddle_indices);
                     let bound_input = pipeline.bind_texture(input);
                     shader_gate.shade(
-                        inversion_shader,
+                        butterfly_shader,
                         |render_gate, iface| {
+                            iface.twiddle_indices.update(&bound_twiddle);
                             iface.input_texture.update(&bound_input);
+                            iface.stage.update(stage.stage);
+                            iface.direction.update(stage.direction);
+                            iface.forward.update(forward as i32);
+                            iface.invert.update(invert as i32);
+                            iface.n.update(N as i32);
                             use luminance::render_state::RenderState;
                             render_gate.render(
                                 RenderState::default(),
@@ -380,7 +461,293 @@ impl Fft {
                     );
                 },
             );
+        });
+    }
+
+    /// Inverse transforms the three layers of `input` into real maps, like
+    /// the ocean's height, velocity and Jacobian spectra. Every stage draws
+    /// all layers at once, so the whole batch takes the same 2·log2(N)
+    /// passes as a single transform.
+    pub fn render_layers<'a>(
+        &self,
+        context: &mut impl GraphicsContext,
+        builder: &Builder,
+        input: &FftLayers,
+        output_buffer: &'a mut LayeredFftFramebuffer,
+    ) -> &'a FftLayers {
+        let Self {
+            tess,
+            twiddle_indices,
+            layered_butterfly_shader,
+            layered_pingpong_buffer,
+            ..
+        } = self;
+        let buffers = [layered_pingpong_buffer, &*output_buffer];
+
+        for_each_stage(|stage| {
+            let layers = match stage.input {
+                Some(buffer) => buffers[buffer].color_slot(),
+                None => input,
+            };
+            builder.pipeline(
+                buffers[stage.output],
+                [1.0, 1.0, 0.0, 1.0],
+                |pipeline, shader_gate| {
+                    let bound_twiddle = pipeline.bind_texture(twiddle_indices);
+                    let bound_layers = (
+                        pipeline.bind_texture(&layers.0),
+                        pipeline.bind_texture(&layers.1),
+                        pipeline.bind_texture(&layers.2),
+                    );
+                    shader_gate.shade(
+                        layered_butterfly_shader,
+                        |render_gate, iface| {
+                            iface.twiddle_indices.update(&bound_twiddle);
+                            iface.layer0.update(&bound_layers.0);
+                            iface.layer1.update(&bound_layers.1);
+                            iface.layer2.update(&bound_layers.2);
+                            iface.stage.update(stage.stage);
+                            iface.direction.update(stage.direction);
+                            iface.forward.update(0);
+                            iface.invert.update(stage.last as i32);
+                            iface.n.update(N as i32);
+                            use luminance::render_state::RenderState;
+                            render_gate.render(
+                                RenderState::default(),
+                                |tess_gate| {
+                                    tess_gate.render(context, tess.into());
+                                },
+                            );
+                        },
+                    );
+                },
+            );
+        });
+        output_buffer.color_slot()
+    }
+
+    /// Circularly convolves `image` with `kernel` by multiplying their
+    /// spectra. The red and green channels are treated as the real and
+    /// imaginary parts of one complex signal, so a real kernel (green left at
+    /// zero) filters two real channels at once. The kernel's origin is the
+    /// texel at (0, 0), and it wraps around the edges like the image does.
+    pub fn convolve<'a>(
+        &self,
+        context: &mut impl GraphicsContext,
+        builder: &Builder,
+        image: &FftTexture,
+        kernel: &FftTexture,
+        output_buffer: &'a mut FftFramebuffer,
+    ) -> &'a FftTexture {
+        let Self {
+            tess,
+            pingpong_buffer,
+            spectrum_buffers,
+            multiply_shader,
+            ..
+        } = self;
+
+        for (input, spectrum) in [image, kernel].iter().zip(spectrum_buffers) {
+            self.butterflies(
+                context,
+                builder,
+                input,
+                [pingpong_buffer, spectrum],
+                true,
+                false,
+            );
         }
+
+        builder.pipeline(
+            &*output_buffer,
+            [0.0, 0.0, 0.0, 1.0],
+            |pipeline, shader_gate| {
+                let bound_lhs =
+                    pipeline.bind_texture(spectrum_buffers[0].color_slot());
+                let bound_rhs =
+                    pipeline.bind_texture(spectrum_buffers[1].color_slot());
+                shader_gate.shade(multiply_shader, |render_gate, iface| {
+                    iface.lhs.update(&bound_lhs);
+                    iface.rhs.update(&bound_rhs);
+                    iface.n.update(N as i32);
+                    use luminance::render_state::RenderState;
+                    render_gate.render(RenderState::default(), |tess_gate| {
+                        tess_gate.render(context, tess.into());
+                    });
+                });
+            },
+        );
+
+        self.butterflies(
+            context,
+            builder,
+            output_buffer.color_slot(),
+            [pingpong_buffer, &*output_buffer],
+            false,
+            false,
+        );
+
         output_buffer.color_slot()
     }
 }
+
+/// One butterfly pass of a 2D transform. `input` and `output` index a pair
+/// of ping-pong buffers; the first pass reads the transform's input instead.
+struct Stage {
+    direction: i32,
+    stage: i32,
+    input: Option<usize>,
+    output: usize,
+    last: bool,
+}
+
+/// Calls `pass` for every butterfly stage along x, then along y. The passes
+/// alternate between two buffers so that the last one writes to the second.
+fn for_each_stage(mut pass: impl FnMut(Stage)) {
+    let bits = (N as f32).log2() as i32;
+    let mut pingpong = 1;
+    let mut first_round = true;
+
+    for &direction in &[0, 1] {
+        for stage in 0..bits {
+            let input = if first_round {
+                first_round = false;
+                None
+            } else {
+                Some(pingpong)
+            };
+            pass(Stage {
+                direction,
+                stage,
+                input,
+                output: 1 - pingpong,
+                last: direction == 1 && stage == bits - 1,
+            });
+            pingpong = 1 - pingpong;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upload(
+        context: &mut impl GraphicsContext,
+        values: &[f32],
+    ) -> FftTexture {
+        let texture =
+            Texture::new(context, [N, N], 0, &Default::default()).unwrap();
+        let texels: Vec<_> =
+            values.iter().map(|&value| (value, 0.0, 0.0, 0.0)).collect();
+        texture.upload(GenMipmaps::No, &texels);
+        texture
+    }
+
+    #[test]
+    #[ignore = "needs a display with OpenGL"]
+    fn convolves_with_a_kernel_of_two_taps() {
+        let sdl = sdl2::init().unwrap();
+        let video_system = sdl.video().unwrap();
+        let window = video_system
+            .window("fft", 1, 1)
+            .opengl()
+            .hidden()
+            .build()
+            .unwrap();
+        let context = &mut crate::SdlContext::new(&video_system, window);
+
+        let n = N as usize;
+        let image: Vec<_> = (0..n * n)
+            .map(|i| ((i % n * 7 + i / n * 13) % 17) as f32)
+            .collect();
+        // Half of each texel stays put, and a quarter of it moves one texel
+        // along x and two along y
+        let mut kernel = vec![0.0; n * n];
+        kernel[0] = 0.5;
+        kernel[2 * n + 1] = 0.25;
+
+        let fft = Fft::new(context);
+        let image_texture = upload(context, &image);
+        let kernel_texture = upload(context, &kernel);
+        let mut output = Framebuffer::new(context, [N, N], 0).unwrap();
+        let builder = context.pipeline_builder();
+        fft.convolve(
+            context,
+            &builder,
+            &image_texture,
+            &kernel_texture,
+            &mut output,
+        );
+
+        let texels = output.color_slot().get_raw_texels();
+        for y in 0..n {
+            for x in 0..n {
+                let from = (y + n - 2) % n * n + (x + n - 1) % n;
+                let expected = 0.5 * image[y * n + x] + 0.25 * image[from];
+                let actual = texels[(y * n + x) * 4];
+                assert!(
+                    (actual - expected).abs() < 1e-3,
+                    "{} instead of {} at {}, {}",
+                    actual,
+                    expected,
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a display with OpenGL"]
+    fn transforms_every_layer() {
+        let sdl = sdl2::init().unwrap();
+        let video_system = sdl.video().unwrap();
+        let window = video_system
+            .window("fft", 1, 1)
+            .opengl()
+            .hidden()
+            .build()
+            .unwrap();
+        let context = &mut crate::SdlContext::new(&video_system, window);
+
+        // A single wave along x per layer, each with its own amplitude
+        let n = N as usize;
+        let spectrum = |amplitude: f32| {
+            let mut values = vec![0.0; n * n];
+            values[1] = amplitude * (n * n) as f32;
+            upload(context, &values)
+        };
+        let layers = (spectrum(1.0), spectrum(2.0), spectrum(3.0));
+
+        let fft = Fft::new(context);
+        let mut output = Framebuffer::new(context, [N, N], 0).unwrap();
+        let builder = context.pipeline_builder();
+        fft.render_layers(context, &builder, &layers, &mut output);
+
+        let (first, second, third) = output.color_slot();
+        let layers = [first, second, third];
+        for (layer, texture) in layers.iter().enumerate() {
+            let texels = texture.get_raw_texels();
+            let amplitude = (layer + 1) as f32;
+            for y in 0..n {
+                for x in 0..n {
+                    let sign = if (x + y) % 2 == 0 { 1.0 } else { -1.0 };
+                    let phase =
+                        std::f32::consts::PI * 2.0 * x as f32 / N as f32;
+                    let expected = sign * amplitude * phase.cos();
+                    let actual = texels[(y * n + x) * 4];
+                    assert!(
+                        (actual - expected).abs() < 1e-3,
+                        "{} instead of {} at {}, {} in layer {}",
+                        actual,
+                        expected,
+                        x,
+                        y,
+                        layer
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use crate::surface::OceanSurface;
+
+/// A CPU copy of a simulated heightmap. Sampling follows
+/// `ocean-surface.glsl`: the map covers one world unit per texel, repeats
+/// every `side` units in both directions and is filtered bilinearly between
+/// texel centers.
+pub struct Heightfield {
+    side: usize,
+    heights: Vec<f32>,
+}
+
+impl Heightfield {
+    /// `heights` is a square map, row by row, as returned by
+    /// `Ocean::read_heightmap`.
+    pub fn new(heights: Vec<f32>) -> Self {
+        let side = (heights.len() as f64).sqrt() as usize;
+        assert_eq!(side * side, heights.len(), "heightmap must be square");
+        Self { side, heights }
+    }
+
+    #[cfg(test)]
+    pub fn heights(&self) -> &[f32] {
+        &self.heights
+    }
+
+    /// Lowest and highest height in the map.
+    pub fn height_range(&self) -> (f32, f32) {
+        self.heights
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &h| {
+                (min.min(h), max.max(h))
+            })
+    }
+
+    fn texel(&self, x: i64, y: i64) -> f32 {
+        let side = self.side as i64;
+        let x = x.rem_euclid(side) as usize;
+        let y = y.rem_euclid(side) as usize;
+        self.heights[y * self.side + x]
+    }
+
+    /// Surface height at world coordinates `x` and `z`.
+    pub fn height_at(&self, x: f32, z: f32) -> f32 {
+        // Texel centers sit half a unit off the vertex grid
+        let x = x - 0.5;
+        let z = z - 0.5;
+        let (x0, z0) = (x.floor(), z.floor());
+        let (fx, fz) = (x - x0, z - z0);
+        let (x0, z0) = (x0 as i64, z0 as i64);
+
+        let top = lerp(self.texel(x0, z0), self.texel(x0 + 1, z0), fx);
+        let bottom =
+            lerp(self.texel(x0, z0 + 1), self.texel(x0 + 1, z0 + 1), fx);
+        lerp(top, bottom, fz)
+    }
+}
+
+impl OceanSurface for Heightfield {
+    fn height_at(&self, x: f32, z: f32) -> f32 {
+        Heightfield::height_at(self, x, z)
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 4 by 4 map whose height is `x + 10 * z` at each texel.
+    fn ramp() -> Heightfield {
+        let heights = (0..16).map(|i| (i % 4 + 10 * (i / 4)) as f32);
+        Heightfield::new(heights.collect())
+    }
+
+    #[test]
+    fn samples_texel_centers_exactly() {
+        let field = ramp();
+        assert_eq!(field.height_at(0.5, 0.5), 0.0);
+        assert_eq!(field.height_at(2.5, 0.5), 2.0);
+        assert_eq!(field.height_at(1.5, 3.5), 31.0);
+    }
+
+    #[test]
+    fn interpolates_between_texels() {
+        let field = ramp();
+        assert_eq!(field.height_at(1.0, 0.5), 0.5);
+        assert_eq!(field.height_at(1.5, 1.0), 6.0);
+        assert_eq!(field.height_at(1.0, 1.0), 5.5);
+    }
+
+    #[test]
+    fn wraps_around_in_both_directions() {
+        let field = ramp();
+        assert_eq!(field.height_at(4.5, 0.5), field.height_at(0.5, 0.5));
+        assert_eq!(field.height_at(-3.5, 0.5), field.height_at(0.5, 0.5));
+        assert_eq!(field.height_at(1.5, -2.5), field.height_at(1.5, 1.5));
+        // Between the last texel and the first one again
+        assert_eq!(field.height_at(4.0, 0.5), 1.5);
+        assert_eq!(field.height_at(0.0, 0.5), 1.5);
+    }
+
+    #[test]
+    fn finds_height_range() {
+        assert_eq!(ramp().height_range(), (0.0, 33.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "heightmap must be square")]
+    fn rejects_maps_that_are_not_square() {
+        Heightfield::new(vec![0.0; 12]);
+    }
+}
@@ -0,0 +1,194 @@
+use luminance::{
+    blending::{Equation, Factor},
+    context::GraphicsContext,
+    framebuffer::Framebuffer,
+    pipeline::{BoundTexture, Builder},
+    pixel::{Floating, R32F, RGBA32F},
+    render_state::RenderState,
+    shader::program::{Program, Uniform},
+    tess::{Mode, Tess, TessBuilder, TessSlice},
+    texture::{Dim2, Flat, GenMipmaps, Texture},
+};
+use luminance_derive::UniformInterface;
+use std::collections::VecDeque;
+
+const RESOLUTION: u32 = 0x100;
+/// Most points of path history kept per vessel
+const MAX_POINTS: usize = 32;
+/// Most vessels drawn at once
+const MAX_VESSELS: usize = 64;
+/// Distance between recorded points of a vessel's path, in world units
+const SPACING: f32 = 4.0;
+
+#[derive(UniformInterface)]
+struct KelvinInterface {
+    paths: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    origin: Uniform<[f32; 2]>,
+    size: Uniform<f32>,
+}
+
+pub type KelvinTexture = Texture<Flat, Dim2, R32F>;
+
+/// A ship leaving a Kelvin wake behind it.
+#[derive(Clone, Debug)]
+pub struct Vessel {
+    /// Height of the wake right behind the ship
+    pub amplitude: f32,
+    /// Speed through the water, in world units per second
+    pub speed: f32,
+    /// World x and z of the ship, then of where it has been, newest first
+    path: VecDeque<glm::Vec2>,
+}
+
+impl Vessel {
+    pub fn new(position: glm::Vec2, amplitude: f32) -> Self {
+        let mut path = VecDeque::with_capacity(MAX_POINTS);
+        path.push_back(position);
+        Self {
+            amplitude,
+            speed: 0.0,
+            path,
+        }
+    }
+
+    /// Moves the ship to `position`, going at `speed`.
+    pub fn record(&mut self, position: glm::Vec2, speed: f32) {
+        self.speed = speed;
+        // The newest point follows the ship until it is far enough from the
+        // one before it to stay behind
+        match self.path.get(1) {
+            Some(previous) if glm::distance(previous, &position) < SPACING => {
+                self.path[0] = position;
+            }
+            _ => {
+                self.path.push_front(position);
+                self.path.truncate(MAX_POINTS);
+            }
+        }
+    }
+
+    /// The header and path texels of the vessel's row of the path texture.
+    fn texels(&self) -> Vec<(f32, f32, f32, f32)> {
+        let mut texels = Vec::with_capacity(MAX_POINTS + 1);
+        let count = self.path.len() as f32;
+        texels.push((self.speed, self.amplitude, count, 0.0));
+        let mut behind = 0.0;
+        let mut previous = self.path[0];
+        for &point in &self.path {
+            behind += glm::distance(&previous, &point);
+            previous = point;
+            texels.push((point.x, point.y, behind, 0.0));
+        }
+        texels.resize(MAX_POINTS + 1, (0.0, 0.0, 0.0, 0.0));
+        texels
+    }
+}
+
+/// Analytic Kelvin wakes of any number of vessels, drawn as heights into a
+/// texture over a square area of the world. Every vessel is one instanced
+/// quad around its path, so many of them cost little more than one.
+pub struct KelvinWakes {
+    pub vessels: Vec<Vessel>,
+    /// World x and z of the area's corner with the lowest coordinates
+    pub origin: glm::Vec2,
+    /// Side of the area, in world units
+    pub size: f32,
+    framebuffer: Framebuffer<Flat, Dim2, R32F, ()>,
+    /// One row per vessel, see `Vessel::texels`
+    paths: Texture<Flat, Dim2, RGBA32F>,
+    shader: Program<(), (), KelvinInterface>,
+    tess: Tess,
+}
+
+impl KelvinWakes {
+    pub fn new(context: &mut impl GraphicsContext) -> Self {
+        let framebuffer =
+            Framebuffer::new(context, [RESOLUTION, RESOLUTION], 0)
+                .expect("framebuffer creation");
+
+        use luminance::texture::{MagFilter, MinFilter, Sampler};
+        let mut sampler = Sampler::default();
+        sampler.mag_filter = MagFilter::Nearest;
+        sampler.min_filter = MinFilter::Nearest;
+        let size = [MAX_POINTS as u32 + 1, MAX_VESSELS as u32];
+        let paths = Texture::new(context, size, 0, &sampler).unwrap();
+
+        let shader = crate::shader::from_strings(
+            include_str!("../shaders/kelvin.vert"),
+            include_str!("../shaders/kelvin.frag"),
+        );
+
+        let tess = TessBuilder::new(context)
+            .set_mode(Mode::TriangleStrip)
+            .set_vertex_nb(4)
+            .build()
+            .unwrap();
+
+        Self {
+            vessels: Vec::new(),
+            origin: glm::vec2(-128.0, -128.0),
+            size: 256.0,
+            framebuffer,
+            paths,
+            shader,
+            tess,
+        }
+    }
+
+    /// Wake heights in the red channel, covering the area from `origin` to
+    /// `origin + size`, as of the last call to `render`.
+    pub fn texture(&self) -> &KelvinTexture {
+        self.framebuffer.color_slot()
+    }
+
+    /// Draws the wakes of the first `MAX_VESSELS` vessels that have moved.
+    pub fn render(
+        &self,
+        context: &mut impl GraphicsContext,
+        builder: &Builder,
+    ) {
+        let moving = self
+            .vessels
+            .iter()
+            .filter(|vessel| vessel.path.len() > 1)
+            .take(MAX_VESSELS);
+        let mut texels = Vec::with_capacity((MAX_POINTS + 1) * MAX_VESSELS);
+        for vessel in moving {
+            texels.extend(vessel.texels());
+        }
+        let count = texels.len() / (MAX_POINTS + 1);
+        texels.resize((MAX_POINTS + 1) * MAX_VESSELS, (0.0, 0.0, 0.0, 0.0));
+        self.paths.upload(GenMipmaps::No, &texels);
+
+        let Self {
+            origin,
+            size,
+            framebuffer,
+            paths,
+            shader,
+            tess,
+            ..
+        } = self;
+        builder.pipeline(
+            framebuffer,
+            [0.0, 0.0, 0.0, 0.0],
+            |pipeline, shader_gate| {
+                let bound_paths = pipeline.bind_texture(paths);
+                shader_gate.shade(shader, |render_gate, iface| {
+                    iface.paths.update(&bound_paths);
+                    iface.origin.update((*origin).into());
+                    iface.size.update(*size);
+                    let state = RenderState::default().set_blending((
+                        Equation::Additive,
+                        Factor::One,
+                        Factor::One,
+                    ));
+                    render_gate.render(state, |tess_gate| {
+                        let slice = TessSlice::inst_whole(tess, count);
+                        tess_gate.render(context, slice);
+                    });
+                });
+            },
+        );
+    }
+}
@@ -0,0 +1,79 @@
+/// The light falling on the scene. Every lit shader starts with
+/// `lighting.glsl`, which declares the matching uniforms, so the same
+/// `Lighting` can be handed to all of them and changed from frame to frame.
+#[derive(Clone, Debug)]
+pub struct Lighting {
+    /// Unit vector pointing at the sun
+    pub sun_direction: glm::Vec3,
+    pub sun_color: glm::Vec3,
+    pub sun_intensity: f32,
+    /// Sky straight up, which also lights the scene from every direction
+    pub sky_color: glm::Vec3,
+    /// Sky at the horizon
+    pub horizon_color: glm::Vec3,
+    /// Fog thickness per world unit at sea level
+    pub fog_density: f32,
+    /// How quickly the fog thins out with height, per world unit
+    pub fog_falloff: f32,
+    /// How much sunlight the fog scatters toward a viewer facing the sun
+    pub fog_sun_glow: f32,
+}
+
+impl Lighting {
+    /// Sunlight at `hours` past midnight, rising in the east along +x and
+    /// setting in the west. The light reddens and fades as the sun nears
+    /// the horizon, and the sky darkens to night below it.
+    pub fn time_of_day(hours: f32) -> Self {
+        use std::f32::consts::PI;
+        let angle = (hours - 6.0) / 12.0 * PI;
+        let sun_direction =
+            glm::normalize(&glm::vec3(angle.cos(), angle.sin(), 0.3));
+
+        let elevation = sun_direction.y;
+        let daylight = (elevation * 5.0 + 0.5).clamp(0.0, 1.0);
+        let noon = (elevation * 3.0).clamp(0.0, 1.0);
+
+        let night_sky = glm::vec3(0.01, 0.02, 0.05);
+        let sunset = glm::vec3(0.9, 0.5, 0.3);
+        Self {
+            sun_direction,
+            sun_color: glm::lerp(
+                &glm::vec3(1.0, 0.5, 0.2),
+                &glm::vec3(1.0, 0.95, 0.85),
+                noon,
+            ),
+            sun_intensity: 3.0 * daylight,
+            sky_color: glm::lerp(
+                &night_sky,
+                &glm::vec3(0.2, 0.4, 0.7),
+                daylight,
+            ),
+            horizon_color: glm::lerp(
+                &night_sky,
+                &glm::lerp(&sunset, &glm::vec3(0.6, 0.7, 0.8), noon),
+                daylight,
+            ),
+            ..Self::default()
+        }
+    }
+
+    /// Sun colour times intensity, as `lighting.glsl` takes it.
+    pub fn sun_radiance(&self) -> glm::Vec3 {
+        self.sun_color * self.sun_intensity
+    }
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Self {
+            sun_direction: glm::normalize(&glm::vec3(1.0, 0.25, 0.0)),
+            sun_color: glm::vec3(1.0, 0.95, 0.85),
+            sun_intensity: 3.0,
+            sky_color: glm::vec3(0.2, 0.4, 0.7),
+            horizon_color: glm::vec3(0.6, 0.7, 0.8),
+            fog_density: 0.004,
+            fog_falloff: 0.05,
+            fog_sun_glow: 0.2,
+        }
+    }
+}
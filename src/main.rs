@@ -6,15 +6,43 @@ use luminance::{
 use sdl2;
 use std::cell::RefCell;
 use std::rc::Rc;
+use surface::OceanSurface;
 
+mod bathymetry;
+mod bodies;
+mod breaking;
 mod camera;
+mod caustics;
+mod clock;
+mod cpu_ocean;
 mod debug;
+mod environment;
 mod fft;
+mod heightfield;
+mod kelvin;
+mod lighting;
+mod material;
 mod ocean;
+mod patches;
+mod physics;
+mod raycast;
+mod readback;
 mod shader;
+mod sky;
+mod spray;
+mod surface;
+mod underwater;
+mod wake;
 
 const SCREEN_WIDTH: u32 = 800;
 const SCREEN_HEIGHT: u32 = 600;
+/// Radius of the circle the boat sails around the origin, and its speed
+const BOAT_RADIUS: f32 = 60.0;
+const BOAT_SPEED: f32 = 6.0;
+/// How close to a breaking crest a floating body gets tossed, and the upward
+/// speed it gets per unit of breaking intensity
+const BREAKER_REACH: f32 = 6.0;
+const BREAKER_TOSS: f32 = 2.0;
 
 struct SdlContext {
     _gl_context: sdl2::video::GLContext,
@@ -29,12 +57,109 @@ unsafe impl GraphicsContext for SdlContext {
 }
 
 impl SdlContext {
+    fn new(
+        video_system: &sdl2::VideoSubsystem,
+        window: sdl2::video::Window,
+    ) -> Self {
+        let _gl_context = window
+            .gl_create_context()
+            .expect("Could not create OpenGL context");
+        gl::load_with(|s| video_system.gl_get_proc_address(s) as *const _);
+
+        let state = GraphicsState::new()
+            .expect("Only one graphics state per thread allowed");
+
+        Self {
+            _gl_context,
+            window,
+            state: Rc::new(RefCell::new(state)),
+        }
+    }
+
     fn swap_buffers(&mut self) {
         self.window.gl_swap_window();
     }
 }
 
+/// Depths around an island off to the side of the boat's circle, as
+/// `Ocean::set_seabed` takes them: the map, then the corner and side of the
+/// area it covers.
+fn island() -> (Vec<f32>, glm::Vec2, f32) {
+    const SIDE: usize = 128;
+    const SIZE: f32 = 256.0;
+    const RADIUS: f32 = 40.0;
+    let center = glm::vec2(-160.0, 120.0);
+    let origin = center - glm::vec2(SIZE, SIZE) / 2.0;
+    let mut depths = Vec::with_capacity(SIDE * SIDE);
+    for y in 0..SIDE {
+        for x in 0..SIDE {
+            let texel = glm::vec2(x as f32 + 0.5, y as f32 + 0.5);
+            let position = origin + texel * (SIZE / SIDE as f32);
+            let offshore = glm::distance(&position, &center) - RADIUS;
+            // The seabed shelves gently off the beach, then drops away to
+            // open sea by the edges of the area
+            depths.push(offshore.signum() * (offshore / 6.0).powi(2));
+        }
+    }
+    (depths, origin, SIZE)
+}
+
+/// The body floating in the water from the start.
+fn raft() -> physics::RigidBody {
+    physics::RigidBody::cuboid(
+        glm::vec3(0.0, 2.0, -10.0),
+        glm::vec3(2.0, 1.0, 4.0),
+        3000.0,
+    )
+}
+
+/// Runs the waves and the bodies on them without a window, the way a game
+/// server without a GPU would, and logs where the raft is once a second.
+fn headless() {
+    use std::time::{Duration, Instant};
+    let mut ocean = cpu_ocean::CpuOcean::new(&Default::default());
+    let mut physics = physics::Physics::default();
+    physics.bodies.push(raft());
+    let mut clock = clock::OceanClock::default();
+    let mut next_report = 0.0;
+
+    let mut previous_tick = Instant::now();
+    loop {
+        std::thread::sleep(Duration::from_secs_f32(clock.step()));
+        let now = Instant::now();
+        let steps = clock.advance((now - previous_tick).as_secs_f32());
+        previous_tick = now;
+        if steps == 0 {
+            continue;
+        }
+
+        ocean.simulate(clock.time());
+        for _ in 0..steps {
+            physics.step(&ocean, clock.step());
+        }
+        if clock.time() >= next_report {
+            next_report += 1.0;
+            let (low, high) = ocean.heightfield().height_range();
+            let raft = physics.bodies[0].position;
+            println!(
+                "{:6.1} s: waves {:.2} to {:.2}, raft at {:.2} {:.2} {:.2}",
+                clock.time(),
+                low,
+                high,
+                raft.x,
+                raft.y,
+                raft.z,
+            );
+        }
+    }
+}
+
 fn main() {
+    if std::env::args().any(|arg| arg == "--headless") {
+        headless();
+        return;
+    }
+
     let sdl = sdl2::init().expect("Could not init sdl2");
 
     let context = {
@@ -48,34 +173,56 @@ fn main() {
             .fullscreen_desktop()
             .build()
             .expect("Could not create window");
-        let _gl_context = window
-            .gl_create_context()
-            .expect("Could not create OpenGL context");
-        gl::load_with(|s| video_system.gl_get_proc_address(s) as *const _);
 
-        let state = GraphicsState::new()
-            .expect("Only one graphics state per thread allowed");
-
-        &mut SdlContext {
-            _gl_context,
-            window,
-            state: Rc::new(RefCell::new(state)),
-        }
+        &mut SdlContext::new(&video_system, window)
     };
 
+    // Filter across the edges of cubemap faces, so neither the sky nor
+    // blurry reflections show seams
+    unsafe {
+        gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
+    }
+
     let (width, height) = context.window.size();
 
     let mut event_pump = sdl.event_pump().unwrap();
     let mut back_buffer = Framebuffer::back_buffer([width, height]);
 
     let mut camera =
-        camera::Camera::persp(width as f32 / height as f32, 0.9, 0.1, 100.0);
+        camera::Camera::persp(width as f32 / height as f32, 0.9, 0.1, 2000.0);
 
-    let mut ocean = ocean::Ocean::new(context);
+    let mut ocean = ocean::Ocean::new(context, &Default::default());
+    let boat = kelvin::Vessel::new(glm::vec2(BOAT_RADIUS, 0.0), 0.5);
+    ocean.kelvin.vessels.push(boat);
+    let (depths, origin, size) = island();
+    ocean.set_seabed(context, depths, origin, size);
+    let breaking = ocean.subscribe_breaking();
+
+    // Physics runs on the last heightmap and velocity that finished reading
+    // back, a frame or two behind the GPU, so it never has to wait for them.
+    // Until the first ones arrive the sea is flat and still
+    let texels = (fft::N * fft::N) as usize;
+    let flat = || heightfield::Heightfield::new(vec![0.0; texels]);
+    let mut heightfield = flat();
+    let mut velocity = [flat(), flat(), flat()];
+    let mut physics = physics::Physics::default();
+    physics.bodies.push(raft());
+
+    let mut clock = clock::OceanClock::default();
+    let mut hours = 15.0;
+    let mut lighting = lighting::Lighting::time_of_day(hours);
+    let mut sky = sky::Sky::new(context);
+    let skybox = sky::Skybox::new(context);
+    let body_renderer = bodies::BodyRenderer::new(context);
+    // An equirectangular .hdr given on the command line replaces the sky
+    let hdr_environment = std::env::args().nth(1).map(|path| {
+        let image = environment::HdrImage::load(&path)
+            .unwrap_or_else(|error| panic!("{}: {}", path, error));
+        environment::prefilter(context, &image)
+    });
 
     use std::time::Instant;
-    let start = Instant::now();
-    let mut previous_frame_start = start;
+    let mut previous_frame_start = Instant::now();
     'app: loop {
         let current_frame_start = Instant::now();
         let delta_t = current_frame_start - previous_frame_start;
@@ -89,8 +236,46 @@ fn main() {
                 }
                 Event::KeyDown { scancode, .. } => {
                     use sdl2::keyboard::Scancode::*;
-                    if let Some(Escape) = scancode {
-                        break 'app;
+                    match scancode {
+                        Some(Escape) => break 'app,
+                        Some(P) => clock.toggle_pause(),
+                        Some(LeftBracket) => clock.time_scale /= 2.0,
+                        Some(RightBracket) => clock.time_scale *= 2.0,
+                        Some(Left) => clock.seek(clock.time() - 1.0),
+                        Some(Right) => clock.seek(clock.time() + 1.0),
+                        Some(Home) => clock.seek(0.0),
+                        Some(M) => {
+                            use ocean::MeshMode::*;
+                            ocean.mesh_mode = match ocean.mesh_mode {
+                                Tiles => ProjectedGrid,
+                                ProjectedGrid => Clipmap,
+                                Clipmap if ocean.supports_tessellation() => {
+                                    Tessellated
+                                }
+                                Clipmap | Tessellated => Tiles,
+                            };
+                        }
+                        Some(Comma) => {
+                            hours = (hours + 23.5) % 24.0;
+                            lighting = lighting::Lighting::time_of_day(hours);
+                        }
+                        Some(Period) => {
+                            hours = (hours + 0.5) % 24.0;
+                            lighting = lighting::Lighting::time_of_day(hours);
+                        }
+                        _ => {}
+                    }
+                }
+                Event::MouseButtonDown { .. } => {
+                    // Drop a crate where the center of the screen points
+                    let (origin, direction) = camera.ray(0.0, 0.0);
+                    let far = camera.far();
+                    if let Some(hit) = ocean.raycast(origin, direction, far) {
+                        physics.bodies.push(physics::RigidBody::cuboid(
+                            hit.position + glm::vec3(0.0, 2.0, 0.0),
+                            glm::vec3(1.0, 1.0, 1.0),
+                            400.0,
+                        ));
                     }
                 }
                 Event::Window { win_event, .. } => {
@@ -108,27 +293,104 @@ fn main() {
             back_buffer = Framebuffer::back_buffer(size);
         }
 
-        camera
-            .take_input(&event_pump, delta_t.as_micros() as f32 / 1_000_000.0);
+        let delta_t = delta_t.as_micros() as f32 / 1_000_000.0;
+        camera.take_input(&event_pump, delta_t);
 
-        let builder = context.pipeline_builder();
+        if let Some(heights) = ocean.poll_heightmap() {
+            heightfield = heightfield::Heightfield::new(heights);
+        }
+        if let Some(polled) = ocean.poll_velocity() {
+            velocity = polled;
+        }
+        let steps = clock.advance(delta_t);
+        for _ in 0..steps {
+            let surface = ocean.surface(&heightfield, &velocity);
+            physics.step(&surface, clock.step());
+        }
+        // Breaking crests toss whatever floats in them. Their positions are
+        // within one heightmap tile and repeat with the waves.
+        let side = fft::N as f32;
+        for event in breaking.try_iter().flatten() {
+            for body in &mut physics.bodies {
+                let offset = (body.position - event.position)
+                    .xz()
+                    .map(|o| (o + side / 2.0).rem_euclid(side) - side / 2.0);
+                if glm::length(&offset) < BREAKER_REACH {
+                    body.velocity.y += event.intensity * BREAKER_TOSS;
+                }
+            }
+        }
+        let simulated_t = steps as f32 * clock.step();
+        for body in &physics.bodies {
+            // Bodies in the water push it aside as they move through it
+            let radius =
+                body.points.iter().map(glm::length).fold(0.0, f32::max);
+            let surface = ocean
+                .surface(&heightfield, &velocity)
+                .height_at(body.position.x, body.position.z);
+            if body.position.y - radius < surface {
+                ocean.wake.disturb(wake::Disturbance {
+                    position: glm::vec2(body.position.x, body.position.z),
+                    radius,
+                    strength: -glm::length(&body.velocity) * simulated_t * 0.5,
+                });
+            }
+        }
+        let angle = clock.time() * BOAT_SPEED / BOAT_RADIUS;
+        let boat = glm::vec2(angle.cos(), angle.sin()) * BOAT_RADIUS;
+        ocean.kelvin.vessels[0].record(boat, BOAT_SPEED);
+        ocean.request_heightmap();
+        let eye = camera.position();
+        let eye_underwater = ocean.is_underwater(&heightfield, eye);
+        ocean.spray.center = glm::vec2(eye.x, eye.z);
 
-        let duration = current_frame_start - start;
-        let f_time = duration.as_secs() as f32
-            + duration.subsec_nanos() as f32 / 1_000_000_000.0;
+        sky.update(&lighting);
+        let environment = hdr_environment.as_ref().unwrap_or(sky.cubemap());
 
-        let ocean_frame = ocean.simulate(context, &builder, f_time);
+        let builder = context.pipeline_builder();
+
+        let ocean_frame = ocean.simulate(
+            context,
+            &builder,
+            clock.time(),
+            &lighting,
+            eye_underwater,
+        );
 
         builder.pipeline(
             &back_buffer,
             [0.1, 0.2, 0.3, 1.0],
             |pipeline, shader_gate| {
                 let view_projection = camera.projection() * camera.view();
-                ocean_frame.render(
+                skybox.render(
                     context,
                     &pipeline,
                     &shader_gate,
                     view_projection,
+                    environment,
+                );
+                body_renderer.render(
+                    context,
+                    &shader_gate,
+                    view_projection,
+                    &physics.bodies,
+                    &lighting,
+                );
+                let view = ocean::FrameView {
+                    view_projection,
+                    eye,
+                    eye_underwater,
+                    lighting: &lighting,
+                    environment,
+                };
+                ocean_frame.render(context, &pipeline, &shader_gate, &view);
+                ocean_frame.render_spray(
+                    context,
+                    &pipeline,
+                    &shader_gate,
+                    camera.view(),
+                    camera.projection(),
+                    &lighting,
                 );
             },
         );
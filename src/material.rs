@@ -0,0 +1,39 @@
+/// How the water surface reflects, scatters and transmits light, as used by
+/// `ocean.frag` and `underwater.glsl`.
+#[derive(Clone, Debug)]
+pub struct WaterMaterial {
+    /// Microfacet roughness of the sun's highlight, from mirror-like at 0
+    pub roughness: f32,
+    /// Sets how much light the surface reflects at each angle
+    pub refractive_index: f32,
+    /// Light scattered back up by a shallow column of water
+    pub shallow_color: glm::Vec3,
+    /// Light scattered back up by deep water
+    pub deep_color: glm::Vec3,
+    /// Length of water the view passes through before the scatter colour
+    /// has mostly turned deep, in world units
+    pub scatter_depth: f32,
+    /// Light shining through the thin water of wave crests
+    pub subsurface_color: glm::Vec3,
+    pub subsurface_strength: f32,
+    /// Height above rest at which crests glow the most
+    pub subsurface_height: f32,
+    /// Light lost per world unit travelled through the water, per channel
+    pub absorption: glm::Vec3,
+}
+
+impl Default for WaterMaterial {
+    fn default() -> Self {
+        Self {
+            roughness: 0.2,
+            refractive_index: 1.333,
+            shallow_color: glm::vec3(0.1, 0.4, 0.4),
+            deep_color: glm::vec3(0.01, 0.05, 0.1),
+            scatter_depth: 20.0,
+            subsurface_color: glm::vec3(0.1, 0.5, 0.4),
+            subsurface_strength: 1.0,
+            subsurface_height: 4.0,
+            absorption: glm::vec3(0.2, 0.06, 0.04),
+        }
+    }
+}
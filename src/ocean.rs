@@ -6,7 +6,7 @@ use luminance::{
     render_state::RenderState,
     shader::program::{Program, Uniform},
     tess::{Mode, Tess, TessBuilder},
-    texture::{Dim2, Flat},
+    texture::{Cubemap, Dim2, Flat},
 };
 use luminance_derive::UniformInterface;
 
@@ -14,7 +14,50 @@ use luminance_derive::UniformInterface;
 pub struct OceanShaderInterface {
     heightmap: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
     view_projection: Uniform<M44>,
-    offset: Uniform<[f32; 2]>,
+    grid_origin: Uniform<[f32; 2]>,
+    grid_spacing: Uniform<f32>,
+    grid_side: Uniform<i32>,
+    #[uniform(unbound)]
+    morph_range: Uniform<[f32; 2]>,
+    hole: Uniform<[f32; 4]>,
+    underwater: Uniform<i32>,
+    absorption: Uniform<[f32; 3]>,
+    caustics_map: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    caustics_depth: Uniform<f32>,
+    #[uniform(unbound)]
+    edge_length: Uniform<f32>,
+    #[uniform(unbound)]
+    projected_grid: Uniform<i32>,
+    #[uniform(unbound)]
+    inverse_view_projection: Uniform<M44>,
+    wake: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    wake_origin: Uniform<[f32; 2]>,
+    wake_size: Uniform<f32>,
+    kelvin: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    kelvin_origin: Uniform<[f32; 2]>,
+    kelvin_size: Uniform<f32>,
+    bathymetry: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    bathymetry_origin: Uniform<[f32; 2]>,
+    bathymetry_size: Uniform<f32>,
+    peak_wavenumber: Uniform<f32>,
+    eye: Uniform<[f32; 3]>,
+    environment:
+        Uniform<&'static BoundTexture<'static, Flat, Cubemap, Floating>>,
+    roughness: Uniform<f32>,
+    refractive_index: Uniform<f32>,
+    shallow_color: Uniform<[f32; 3]>,
+    deep_color: Uniform<[f32; 3]>,
+    scatter_depth: Uniform<f32>,
+    subsurface_color: Uniform<[f32; 3]>,
+    subsurface_strength: Uniform<f32>,
+    subsurface_height: Uniform<f32>,
+    sun_direction: Uniform<[f32; 3]>,
+    sun_radiance: Uniform<[f32; 3]>,
+    sky_color: Uniform<[f32; 3]>,
+    horizon_color: Uniform<[f32; 3]>,
+    fog_density: Uniform<f32>,
+    fog_falloff: Uniform<f32>,
+    fog_sun_glow: Uniform<f32>,
 }
 
 impl OceanShaderInterface {
@@ -22,81 +65,357 @@ impl OceanShaderInterface {
         self.view_projection.update(value);
     }
 
-    pub fn set_offset(&self, value: [f32; 2]) {
-        self.offset.update(value);
+    /// Lays out a mesh made by `grid_tess` with `side` quads per row, from
+    /// `origin` onward. Odd vertices slide onto even ones over `morph_range`
+    /// of distances from the eye, if there is one.
+    pub fn set_grid(
+        &self,
+        origin: glm::Vec2,
+        spacing: f32,
+        side: u32,
+        morph_range: Option<(f32, f32)>,
+    ) {
+        self.grid_origin.update(origin.into());
+        self.grid_spacing.update(spacing);
+        self.grid_side.update(side as i32);
+        let (start, end) = morph_range.unwrap_or((f32::MAX / 2.0, f32::MAX));
+        self.morph_range.update([start, end]);
+    }
+
+    pub fn set_caustics(
+        &self,
+        value: &BoundTexture<Flat, Dim2, Floating>,
+        depth: f32,
+    ) {
+        self.caustics_map.update(value);
+        self.caustics_depth.update(depth);
+    }
+
+    pub fn set_underwater(&self, value: bool) {
+        self.underwater.update(value as i32);
+    }
+
+    pub fn set_edge_length(&self, value: f32) {
+        self.edge_length.update(value);
+    }
+
+    /// Leaves out the part of the surface between `min` and `max`.
+    pub fn set_hole(&self, min: glm::Vec2, max: glm::Vec2) {
+        self.hole.update([min.x, min.y, max.x, max.y]);
+    }
+
+    /// Lays the mesh out over the sea as seen through `view_projection`
+    /// rather than over the tile at `offset`.
+    pub fn set_projected_grid(&self, view_projection: Option<glm::Mat4>) {
+        self.projected_grid.update(view_projection.is_some() as i32);
+        if let Some(view_projection) = view_projection {
+            let inverse = glm::inverse(&view_projection);
+            self.inverse_view_projection.update(inverse.into());
+        }
     }
 
     pub fn set_heightmap(&self, value: &BoundTexture<Flat, Dim2, Floating>) {
         self.heightmap.update(value);
     }
+
+    pub fn set_wake(&self, value: &BoundTexture<Flat, Dim2, Floating>) {
+        self.wake.update(value);
+    }
+
+    pub fn set_wake_area(&self, origin: [f32; 2], size: f32) {
+        self.wake_origin.update(origin);
+        self.wake_size.update(size);
+    }
+
+    pub fn set_kelvin(&self, value: &BoundTexture<Flat, Dim2, Floating>) {
+        self.kelvin.update(value);
+    }
+
+    pub fn set_kelvin_area(&self, origin: [f32; 2], size: f32) {
+        self.kelvin_origin.update(origin);
+        self.kelvin_size.update(size);
+    }
+
+    pub fn set_bathymetry(&self, value: &BoundTexture<Flat, Dim2, Floating>) {
+        self.bathymetry.update(value);
+    }
+
+    pub fn set_bathymetry_area(&self, origin: [f32; 2], size: f32) {
+        self.bathymetry_origin.update(origin);
+        self.bathymetry_size.update(size);
+    }
+
+    pub fn set_peak_wavenumber(&self, value: f32) {
+        self.peak_wavenumber.update(value);
+    }
+
+    pub fn set_eye(&self, value: [f32; 3]) {
+        self.eye.update(value);
+    }
+
+    pub fn set_environment(
+        &self,
+        value: &BoundTexture<Flat, Cubemap, Floating>,
+    ) {
+        self.environment.update(value);
+    }
+
+    pub fn set_material(&self, material: &WaterMaterial) {
+        self.roughness.update(material.roughness);
+        self.refractive_index.update(material.refractive_index);
+        self.shallow_color.update(material.shallow_color.into());
+        self.deep_color.update(material.deep_color.into());
+        self.scatter_depth.update(material.scatter_depth);
+        self.subsurface_color
+            .update(material.subsurface_color.into());
+        self.subsurface_strength
+            .update(material.subsurface_strength);
+        self.subsurface_height.update(material.subsurface_height);
+        self.absorption.update(material.absorption.into());
+    }
+
+    pub fn set_lighting(&self, lighting: &Lighting) {
+        self.sun_direction.update(lighting.sun_direction.into());
+        self.sun_radiance.update(lighting.sun_radiance().into());
+        self.sky_color.update(lighting.sky_color.into());
+        self.horizon_color.update(lighting.horizon_color.into());
+        self.fog_density.update(lighting.fog_density);
+        self.fog_falloff.update(lighting.fog_falloff);
+        self.fog_sun_glow.update(lighting.fog_sun_glow);
+    }
 }
 
 type OceanShader = Program<(), (), OceanShaderInterface>;
 
-use crate::fft::{Fft, FftFramebuffer, H0k, Hkt};
+use crate::bathymetry::{Bathymetry, MAX_GAIN};
+use crate::breaking::{BreakingDetector, BreakingEvent};
+use crate::caustics::Caustics;
+use crate::fft::{
+    Fft, FftTexture, H0k, Hkt, LayeredFftFramebuffer, Spectrum, N,
+};
+use crate::heightfield::Heightfield;
+use crate::kelvin::KelvinWakes;
+use crate::lighting::Lighting;
+use crate::material::WaterMaterial;
+use crate::patches::PatchMesh;
+use crate::raycast::RayHit;
+use crate::readback::AsyncReadback;
+use crate::sky::EnvironmentMap;
+use crate::spray::Spray;
+use crate::surface::OceanSurface;
+use crate::underwater::Underwater;
+use crate::wake::Wake;
+use std::cell::{Ref, RefCell};
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+/// Side of one tile of the ocean mesh, in world units
+const TILE_SIZE: f32 = 256.0;
+/// Which tiles `OceanFrame::render` draws, along both x and z
+const TILES: std::ops::Range<i32> = -1..1;
+/// Quads along each side of a clipmap level
+const CLIPMAP_SIDE: u32 = 128;
+const CLIPMAP_LEVELS: u32 = 6;
+/// Side of a tessellated patch, in world units
+const PATCH_SIZE: f32 = 16.0;
+
+/// How `OceanFrame::render` lays out the ocean mesh.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MeshMode {
+    /// A fixed block of tiles around the origin
+    Tiles,
+    /// A grid spread over the screen and projected onto the sea, reaching
+    /// the horizon wherever the camera is
+    ProjectedGrid,
+    /// Square rings around the camera, each with twice the spacing of the
+    /// one inside it
+    Clipmap,
+    /// The tiles as coarse patches, tessellated on the GPU until their edges
+    /// are short on screen. Needs OpenGL 4.0, without which `Tiles` are drawn
+    Tessellated,
+}
+
+/// Distance between vertices of clipmap `level`, starting from one heightmap
+/// texel.
+fn clipmap_spacing(level: u32) -> f32 {
+    (1 << level) as f32 * TILE_SIZE / N as f32
+}
+
+/// How far the outermost clipmap level reaches from the camera.
+fn clipmap_reach() -> f32 {
+    (CLIPMAP_SIDE / 2) as f32 * clipmap_spacing(CLIPMAP_LEVELS - 1)
+}
+
+/// Where clipmap `level` starts, in whole steps of twice its spacing so its
+/// vertices stay on the same heightmap texels as the camera moves.
+fn clipmap_origin(eye: glm::Vec3, level: u32) -> glm::Vec2 {
+    let spacing = clipmap_spacing(level);
+    let step = 2.0 * spacing;
+    let snapped = glm::vec2((eye.x / step).floor(), (eye.z / step).floor());
+    snapped * step - glm::vec2(1.0, 1.0) * (CLIPMAP_SIDE / 2) as f32 * spacing
+}
+
+/// A grid of `side` by `side` quads as triangle strips, with the quads in
+/// `hole` along both axes left out.
+fn grid_tess(
+    context: &mut impl GraphicsContext,
+    side: u32,
+    hole: std::ops::Range<u32>,
+) -> Tess {
+    let line_count = side + 1;
+    let restart = u32::max_value();
+    let mut indices = Vec::new();
+    for x in 0..side {
+        let mut in_strip = false;
+        for y in 0..side {
+            if hole.contains(&x) && hole.contains(&y) {
+                if in_strip {
+                    indices.push(restart);
+                    in_strip = false;
+                }
+                continue;
+            }
+            if !in_strip {
+                indices.push(x * line_count + y);
+                indices.push(x * line_count + y + line_count);
+                in_strip = true;
+            }
+            indices.push(x * line_count + y + 1);
+            indices.push(x * line_count + y + 1 + line_count);
+        }
+        if in_strip && x + 1 != side {
+            indices.push(restart);
+        }
+    }
+
+    TessBuilder::new(context)
+        .set_mode(Mode::TriangleStrip)
+        .set_primitive_restart_index(Some(restart))
+        .set_vertex_nb(indices.len())
+        .set_indices(indices)
+        .build()
+        .unwrap()
+}
+
 pub struct Ocean {
     pub h0k: H0k,
     pub hkt: Hkt,
     pub fft: Fft,
-    pub heightmap_buffer: FftFramebuffer,
+    /// The height, velocity and Jacobian maps, in that order, laid out like
+    /// the spectra in `Hkt`'s color slots
+    pub surface_buffer: LayeredFftFramebuffer,
+    pub breaking: BreakingDetector,
+    breaking_subscribers: Vec<Sender<Vec<BreakingEvent>>>,
+    pub spray: Spray,
+    pub wake: Wake,
+    pub kelvin: KelvinWakes,
+    pub caustics: Caustics,
+    pub material: WaterMaterial,
+    pub mesh_mode: MeshMode,
+    /// How long on screen, in normalized device coordinates, edges of
+    /// tessellated patches may be
+    pub edge_length: f32,
+    bathymetry: Bathymetry,
+    /// Time of the last call to `simulate`
+    last_time: Option<f32>,
+    heightmap_readback: AsyncReadback,
+    velocity_readback: AsyncReadback,
+    readback_requested: bool,
+    heightfield: RefCell<Option<Heightfield>>,
+    velocity_field: RefCell<Option<[Heightfield; 3]>>,
     shader: OceanShader,
     tess: Tess,
+    clipmap_center: Tess,
+    clipmap_ring: Tess,
+    tessellation: Option<(OceanShader, PatchMesh)>,
+    underwater: Underwater,
 }
 
 impl Ocean {
-    pub fn new(context: &mut impl GraphicsContext) -> Self {
-        let h0k = H0k::new(context);
+    pub fn new(
+        context: &mut impl GraphicsContext,
+        spectrum: &Spectrum,
+    ) -> Self {
+        let h0k = H0k::new(context, spectrum);
         {
             let builder = context.pipeline_builder();
             h0k.render(context, &builder);
         }
-        let hkt = Hkt::new(context);
+        let hkt = Hkt::new(context, spectrum);
         let fft = Fft::new(context);
-        let heightmap_buffer = FftFramebuffer::new(context, [0x100, 0x100], 0)
+        let surface_buffer = LayeredFftFramebuffer::new(context, [N, N], 0)
             .expect("framebuffer creation");
+        let breaking = BreakingDetector::new(context);
+        let spray = Spray::new(context);
+        let wake = Wake::new(context);
+        let kelvin = KelvinWakes::new(context);
+        let caustics = Caustics::new(context);
+        let bathymetry = Bathymetry::deep(context, spectrum);
+        let heightmap_readback = AsyncReadback::new(context, 0x100, 0x100);
+        let velocity_readback = AsyncReadback::rgba(context, 0x100, 0x100);
         let shader = crate::shader::from_strings(
-            include_str!("../shaders/ocean.vert"),
-            include_str!("../shaders/ocean.frag"),
+            concat!(
+                include_str!("../shaders/ocean-surface.glsl"),
+                include_str!("../shaders/ocean.vert"),
+            ),
+            concat!(
+                include_str!("../shaders/lighting.glsl"),
+                include_str!("../shaders/underwater.glsl"),
+                include_str!("../shaders/ocean.frag"),
+            ),
+        );
+        let tessellation = PatchMesh::new().map(|patches| {
+            let shader = crate::shader::from_strings_with_tessellation(
+                include_str!("../shaders/ocean-patch.vert"),
+                include_str!("../shaders/ocean.tesc"),
+                concat!(
+                    include_str!("../shaders/ocean.tese"),
+                    include_str!("../shaders/ocean-surface.glsl"),
+                ),
+                concat!(
+                    include_str!("../shaders/lighting.glsl"),
+                    include_str!("../shaders/underwater.glsl"),
+                    include_str!("../shaders/ocean.frag"),
+                ),
+            );
+            (shader, patches)
+        });
+        let tess = grid_tess(context, N, 0..0);
+        let clipmap_center = grid_tess(context, CLIPMAP_SIDE, 0..0);
+        // Whichever way the finer level inside is snapped, it covers these
+        // quads; the fragment shader discards the rest of what it covers
+        let clipmap_ring = grid_tess(
+            context,
+            CLIPMAP_SIDE,
+            CLIPMAP_SIDE / 4 + 1..CLIPMAP_SIDE * 3 / 4,
         );
-        let tess = {
-            let side: usize = 0x100;
-            let line_count = side + 1;
-
-            let restart = u32::max_value();
-            let indices = {
-                let mut indices =
-                    Vec::with_capacity(side * (line_count * 2 + 1) - 1);
-                let side = side as u32;
-                let line_count = line_count as u32;
-                for x in 0..side {
-                    if x != 0 {
-                        indices.push(restart);
-                    }
-                    for y in 0..line_count {
-                        indices.push(x * line_count + y);
-                        indices.push(x * line_count + y + line_count);
-                    }
-                }
-                assert_eq!(indices.len(), indices.capacity());
-                indices
-            };
-
-            TessBuilder::new(context)
-                .set_mode(Mode::TriangleStrip)
-                .set_primitive_restart_index(Some(restart))
-                .set_vertex_nb(indices.len())
-                .set_indices(indices)
-                .build()
-                .unwrap()
-        };
 
         Self {
             h0k,
             hkt,
             fft,
-            heightmap_buffer,
+            surface_buffer,
+            breaking,
+            breaking_subscribers: Vec::new(),
+            spray,
+            wake,
+            kelvin,
+            caustics,
+            material: Default::default(),
+            mesh_mode: MeshMode::Tiles,
+            edge_length: 0.02,
+            bathymetry,
+            last_time: None,
+            heightmap_readback,
+            velocity_readback,
+            readback_requested: false,
+            heightfield: RefCell::new(None),
+            velocity_field: RefCell::new(None),
             shader,
             tess,
+            clipmap_center,
+            clipmap_ring,
+            tessellation,
+            underwater: Underwater::new(context),
         }
     }
 
@@ -105,54 +424,477 @@ impl Ocean {
         context: &mut impl GraphicsContext,
         builder: &Builder,
         time: f32,
+        lighting: &Lighting,
+        eye_underwater: bool,
     ) -> OceanFrame {
         let Self {
             h0k,
             hkt,
             fft,
-            heightmap_buffer,
+            surface_buffer,
+            breaking,
+            spray,
+            wake,
+            kelvin,
+            caustics,
+            material,
+            last_time,
+            heightmap_readback,
+            velocity_readback,
+            readback_requested,
+            heightfield,
+            velocity_field,
             ..
         } = self;
-        hkt.render(context, builder, time, h0k.framebuffer.color_slot());
-        fft.render(
-            context,
-            builder,
-            hkt.framebuffer.color_slot(),
-            heightmap_buffer,
-        );
+        *heightfield.get_mut() = None;
+        *velocity_field.get_mut() = None;
+        let spectra =
+            hkt.render(context, builder, time, h0k.framebuffer.color_slot());
+        let maps = fft.render_layers(context, builder, spectra, surface_buffer);
+        let (heightmap, velocity, jacobian) = maps;
+        if *readback_requested {
+            *readback_requested = false;
+            heightmap_readback.issue(builder, heightmap);
+            velocity_readback.issue(builder, velocity);
+        }
+        breaking.detect(context, builder, heightmap, velocity, jacobian);
+        // Don't let a long hitch send a burst of substeps through the wake
+        let dt = last_time.map_or(0.0, |last| (time - last).max(0.0).min(0.1));
+        *last_time = Some(time);
+        spray.update(context, builder, dt, breaking, maps);
+        wake.step(context, builder, dt, fft);
+        kelvin.render(context, builder);
+        // Only the water around an eye under the surface shows caustics
+        if eye_underwater {
+            caustics.render(
+                context,
+                builder,
+                heightmap,
+                material.refractive_index,
+                lighting,
+            );
+        }
+        self.dispatch_breaking_events();
         OceanFrame(self)
     }
+
+    /// The heightmap written by the last call to `simulate`
+    pub fn heightmap(&self) -> &FftTexture {
+        &self.surface_buffer.color_slot().0
+    }
+
+    /// Reads the heightmap written by the last call to `simulate`, one
+    /// height per texel, row by row. Waits for the GPU to finish rendering
+    /// it, so prefer `request_heightmap` where a stall matters.
+    pub fn read_heightmap(&self) -> Vec<f32> {
+        let texels = self.heightmap().get_raw_texels();
+        texels.chunks(4).map(|texel| texel[0]).collect()
+    }
+
+    /// Asks for a copy of the heightmap and surface velocity written by the
+    /// next call to `simulate`. They become available through
+    /// `poll_heightmap` and `poll_velocity` once the GPU is done with them,
+    /// usually a frame or two later.
+    pub fn request_heightmap(&mut self) {
+        self.readback_requested = true;
+    }
+
+    /// Returns the oldest requested heightmap that has finished copying, in
+    /// the same layout as `read_heightmap`.
+    pub fn poll_heightmap(&mut self) -> Option<Vec<f32>> {
+        self.heightmap_readback.poll()
+    }
+
+    /// Returns the surface velocity copied along with the oldest requested
+    /// heightmap, split into components like `velocity_field`.
+    pub fn poll_velocity(&mut self) -> Option<[Heightfield; 3]> {
+        let texels = self.velocity_readback.poll()?;
+        let component = |channel: usize| {
+            Heightfield::new(texels.chunks(4).map(|t| t[channel]).collect())
+        };
+        // Red, green and blue hold the velocity along x, z and y
+        Some([component(0), component(2), component(1)])
+    }
+
+    /// The heightmap of the last simulated frame, read back the first time
+    /// it is asked for.
+    pub fn heightfield(&self) -> Ref<Heightfield> {
+        if self.heightfield.borrow().is_none() {
+            let heights = self.read_heightmap();
+            self.heightfield.replace(Some(Heightfield::new(heights)));
+        }
+        Ref::map(self.heightfield.borrow(), |heightfield| {
+            heightfield.as_ref().unwrap()
+        })
+    }
+
+    /// Reads the surface velocity written by the last call to `simulate`,
+    /// as x, y and z components per texel, row by row. Stalls like
+    /// `read_heightmap`.
+    pub fn read_velocity(&self) -> Vec<glm::Vec3> {
+        let texels = self.surface_buffer.color_slot().1.get_raw_texels();
+        texels
+            .chunks(4)
+            .map(|texel| glm::vec3(texel[0], texel[2], texel[1]))
+            .collect()
+    }
+
+    /// The x, y and z components of the surface velocity of the last
+    /// simulated frame, each sampled like a heightmap.
+    pub fn velocity_field(&self) -> Ref<[Heightfield; 3]> {
+        if self.velocity_field.borrow().is_none() {
+            let velocity = self.read_velocity();
+            let component = |i: usize| {
+                Heightfield::new(velocity.iter().map(|v| v[i]).collect())
+            };
+            let field = [component(0), component(1), component(2)];
+            self.velocity_field.replace(Some(field));
+        }
+        Ref::map(self.velocity_field.borrow(), |field| {
+            field.as_ref().unwrap()
+        })
+    }
+
+    /// Receives the breaking events found in every simulated frame, as one
+    /// list per frame, a frame or two after it was simulated. The receiver
+    /// may live on another thread, and unsubscribes by being dropped.
+    pub fn subscribe_breaking(&mut self) -> Receiver<Vec<BreakingEvent>> {
+        let (sender, receiver) = channel();
+        self.breaking_subscribers.push(sender);
+        receiver
+    }
+
+    fn dispatch_breaking_events(&mut self) {
+        while let Some(events) = self.breaking.poll() {
+            self.breaking_subscribers
+                .retain(|subscriber| subscriber.send(events.clone()).is_ok());
+        }
+    }
+
+    /// Gives the ocean a seabed. `depths` is a square map of depths below the
+    /// rest surface, row by row, covering the area from `origin` to
+    /// `origin + size` in world x and z. Zero or less is land.
+    pub fn set_seabed(
+        &mut self,
+        context: &mut impl GraphicsContext,
+        depths: Vec<f32>,
+        origin: glm::Vec2,
+        size: f32,
+    ) {
+        let spectrum = self.h0k.spectrum();
+        self.bathymetry =
+            Bathymetry::new(context, spectrum, depths, origin, size);
+    }
+
+    /// Whether `eye` is below the surface in `heightfield`, normally one
+    /// from `poll_heightmap` so the answer never waits for the GPU.
+    pub fn is_underwater(
+        &self,
+        heightfield: &Heightfield,
+        eye: glm::Vec3,
+    ) -> bool {
+        eye.y < self.shoaled_height_at(heightfield, eye.x, eye.z)
+    }
+
+    /// `heightfield` and `velocity`, normally from `poll_heightmap` and
+    /// `poll_velocity`, as the surface `OceanFrame::render` draws from them.
+    pub fn surface<'a>(
+        &'a self,
+        heightfield: &'a Heightfield,
+        velocity: &'a [Heightfield; 3],
+    ) -> PolledSurface<'a> {
+        PolledSurface {
+            ocean: self,
+            heightfield,
+            velocity,
+        }
+    }
+
+    /// Height of `heightfield` at `x` and `z` once the seabed has bent and
+    /// shoaled its waves, as `OceanFrame::render` draws them.
+    fn shoaled_height_at(
+        &self,
+        heightfield: &Heightfield,
+        x: f32,
+        z: f32,
+    ) -> f32 {
+        let warped = self.bathymetry.warp(x, z);
+        let height = heightfield.height_at(warped.x, warped.y);
+        self.bathymetry.shoal(height, x, z)
+    }
+
+    /// Whether `MeshMode::Tessellated` can be drawn.
+    pub fn supports_tessellation(&self) -> bool {
+        self.tessellation.is_some()
+    }
+
+    pub fn bathymetry(&self) -> &Bathymetry {
+        &self.bathymetry
+    }
+
+    /// Casts a ray against the surface as drawn by `OceanFrame::render` for
+    /// the last simulated frame. Rays that leave the drawn tiles miss, or
+    /// with meshes that follow the camera, rays that go further from their
+    /// origin than `view_distance`, normally the camera's far plane.
+    pub fn raycast(
+        &self,
+        origin: glm::Vec3,
+        direction: glm::Vec3,
+        view_distance: f32,
+    ) -> Option<RayHit> {
+        // Shoaling may raise any wave up to MAX_GAIN times, or flatten it
+        // to nothing on land
+        let (low, high) = self.heightfield().height_range();
+        let (low, high) = (low.min(0.0) * MAX_GAIN, high.max(0.0) * MAX_GAIN);
+        let (min, max) = match self.mesh_mode {
+            MeshMode::Tiles | MeshMode::Tessellated => {
+                let min = TILES.start as f32 * TILE_SIZE;
+                let max = TILES.end as f32 * TILE_SIZE;
+                (glm::vec2(min, min), glm::vec2(max, max))
+            }
+            MeshMode::ProjectedGrid | MeshMode::Clipmap => {
+                let reach = if self.mesh_mode == MeshMode::Clipmap {
+                    view_distance.min(clipmap_reach())
+                } else {
+                    view_distance
+                };
+                let center = glm::vec2(origin.x, origin.z);
+                let range = glm::vec2(1.0, 1.0) * reach;
+                (center - range, center + range)
+            }
+        };
+        crate::raycast::raycast(
+            self,
+            origin,
+            direction,
+            glm::vec3(min.x, low, min.y),
+            glm::vec3(max.x, high, max.y),
+        )
+    }
+}
+
+impl OceanSurface for Ocean {
+    fn height_at(&self, x: f32, z: f32) -> f32 {
+        self.shoaled_height_at(&self.heightfield(), x, z)
+    }
+
+    fn surface_velocity_at(&self, x: f32, z: f32) -> glm::Vec3 {
+        let [vx, vy, vz] = &*self.velocity_field();
+        glm::vec3(vx.height_at(x, z), vy.height_at(x, z), vz.height_at(x, z))
+    }
+
+    fn peak_wavenumber(&self) -> f32 {
+        self.h0k.spectrum().peak_wavenumber()
+    }
+}
+
+/// A heightfield and velocity field read back from an `Ocean`, with the
+/// heights warped and shoaled by its seabed like the drawn surface.
+pub struct PolledSurface<'a> {
+    ocean: &'a Ocean,
+    heightfield: &'a Heightfield,
+    velocity: &'a [Heightfield; 3],
+}
+
+impl OceanSurface for PolledSurface<'_> {
+    fn height_at(&self, x: f32, z: f32) -> f32 {
+        self.ocean.shoaled_height_at(self.heightfield, x, z)
+    }
+
+    fn surface_velocity_at(&self, x: f32, z: f32) -> glm::Vec3 {
+        let [vx, vy, vz] = self.velocity;
+        glm::vec3(vx.height_at(x, z), vy.height_at(x, z), vz.height_at(x, z))
+    }
+
+    fn peak_wavenumber(&self) -> f32 {
+        self.ocean.peak_wavenumber()
+    }
+}
+
+/// How a frame looks at the ocean, and the light it is drawn in.
+pub struct FrameView<'a> {
+    pub view_projection: glm::Mat4,
+    pub eye: glm::Vec3,
+    pub eye_underwater: bool,
+    pub lighting: &'a Lighting,
+    pub environment: &'a EnvironmentMap,
 }
 
 pub struct OceanFrame<'a>(&'a Ocean);
 
 impl<'a> OceanFrame<'a> {
+    /// Draws the surface, from beneath when the eye is underwater, along
+    /// with the water around the eye and the waterline.
     pub fn render(
         &self,
         context: &mut impl GraphicsContext,
         pipeline: &Pipeline,
         shader_gate: &ShadingGate,
-        view_projection: impl Into<M44>,
+        view: &FrameView,
     ) {
-        let Self(Ocean {
-            heightmap_buffer,
+        let Self(ocean) = self;
+        let FrameView {
+            view_projection,
+            eye,
+            eye_underwater,
+            lighting,
+            environment,
+        } = *view;
+        let Ocean {
+            wake,
+            kelvin,
+            caustics,
+            material,
+            mesh_mode,
+            bathymetry,
+            h0k,
             shader,
             tess,
+            clipmap_center,
+            clipmap_ring,
+            tessellation,
+            edge_length,
+            underwater,
             ..
-        }) = self;
+        } = ocean;
 
-        let heightmap = pipeline.bind_texture(heightmap_buffer.color_slot());
+        let (shader, mesh_mode) = match (mesh_mode, tessellation) {
+            (MeshMode::Tessellated, Some((patch_shader, _))) => {
+                (patch_shader, MeshMode::Tessellated)
+            }
+            (MeshMode::Tessellated, None) => (shader, MeshMode::Tiles),
+            (mode, _) => (shader, *mode),
+        };
+
+        underwater.render_volume(context, pipeline, shader_gate, ocean, view);
+
+        let heightmap = pipeline.bind_texture(ocean.heightmap());
+        let wake_texture = pipeline.bind_texture(wake.texture());
+        let kelvin_texture = pipeline.bind_texture(kelvin.texture());
+        let seabed = pipeline.bind_texture(bathymetry.texture());
+        let caustics_texture = pipeline.bind_texture(caustics.texture());
+        let bound_environment = pipeline.bind_texture(environment);
         shader_gate.shade(shader, |render_gate, iface| {
             iface.set_view_projection(view_projection.into());
             iface.set_heightmap(&heightmap);
+            iface.set_wake(&wake_texture);
+            iface.set_wake_area(wake.origin.into(), wake.size);
+            iface.set_kelvin(&kelvin_texture);
+            iface.set_kelvin_area(kelvin.origin.into(), kelvin.size);
+            iface.set_bathymetry(&seabed);
+            iface.set_bathymetry_area(
+                bathymetry.origin().into(),
+                bathymetry.size(),
+            );
+            iface.set_peak_wavenumber(h0k.spectrum().peak_wavenumber());
+            iface.set_eye(eye.into());
+            iface.set_underwater(eye_underwater);
+            iface.set_caustics(&caustics_texture, caustics.depth);
+            iface.set_material(material);
+            iface.set_lighting(lighting);
+            iface.set_environment(&bound_environment);
             render_gate.render(RenderState::default(), |tess_gate| {
-                for x in -1..1 {
-                    for y in -1..1 {
-                        iface.set_offset([x as f32, y as f32]);
+                let no_hole = glm::vec2(0.0, 0.0);
+                iface.set_hole(no_hole, no_hole);
+                iface.set_projected_grid(None);
+                match mesh_mode {
+                    MeshMode::Tiles => {
+                        for x in TILES {
+                            for y in TILES {
+                                let origin = glm::vec2(x as f32, y as f32);
+                                iface.set_grid(
+                                    origin * TILE_SIZE,
+                                    1.0,
+                                    N,
+                                    None,
+                                );
+                                tess_gate.render(context, tess.into());
+                            }
+                        }
+                    }
+                    MeshMode::Tessellated => {
+                        let (_, patches) = tessellation.as_ref().unwrap();
+                        let side = (TILE_SIZE / PATCH_SIZE) as u32;
+                        iface.set_edge_length(*edge_length);
+                        for x in TILES {
+                            for y in TILES {
+                                let origin = glm::vec2(x as f32, y as f32);
+                                iface.set_grid(
+                                    origin * TILE_SIZE,
+                                    PATCH_SIZE,
+                                    side,
+                                    None,
+                                );
+                                patches.draw(side * side);
+                            }
+                        }
+                    }
+                    MeshMode::ProjectedGrid => {
+                        iface.set_grid(glm::zero(), 1.0, N, None);
+                        iface.set_projected_grid(Some(view_projection));
                         tess_gate.render(context, tess.into());
                     }
+                    MeshMode::Clipmap => {
+                        for level in 0..CLIPMAP_LEVELS {
+                            let spacing = clipmap_spacing(level);
+                            // The last level has nothing coarser to become
+                            let morph_range = if level + 1 < CLIPMAP_LEVELS {
+                                let end = (CLIPMAP_SIDE / 2 - 2) as f32;
+                                let start = end - (CLIPMAP_SIDE / 8) as f32;
+                                Some((start * spacing, end * spacing))
+                            } else {
+                                None
+                            };
+                            iface.set_grid(
+                                clipmap_origin(eye, level),
+                                spacing,
+                                CLIPMAP_SIDE,
+                                morph_range,
+                            );
+                            if level == 0 {
+                                tess_gate
+                                    .render(context, clipmap_center.into());
+                            } else {
+                                let min = clipmap_origin(eye, level - 1);
+                                let extent =
+                                    CLIPMAP_SIDE as f32 * spacing / 2.0;
+                                let max = min + glm::vec2(extent, extent);
+                                iface.set_hole(min, max);
+                                tess_gate.render(context, clipmap_ring.into());
+                            }
+                        }
+                    }
                 }
             });
-        })
+        });
+
+        underwater.render_waterline(
+            context,
+            pipeline,
+            shader_gate,
+            ocean,
+            view,
+        );
+    }
+
+    /// Draws the spray, after the ocean so the water hides what is under it.
+    pub fn render_spray(
+        &self,
+        context: &mut impl GraphicsContext,
+        pipeline: &Pipeline,
+        shader_gate: &ShadingGate,
+        view: impl Into<M44>,
+        projection: impl Into<M44>,
+        lighting: &Lighting,
+    ) {
+        let Self(ocean) = self;
+        ocean.spray.render(
+            context,
+            pipeline,
+            shader_gate,
+            view,
+            projection,
+            lighting,
+        );
     }
 }
@@ -0,0 +1,49 @@
+use gl::types::{GLint, GLsizei, GLuint};
+
+/// Draws quad patches for the tessellation stages, which luminance has no
+/// primitive mode for. Patches are four vertices without attributes, placed
+/// by the vertex shader from `gl_VertexID`.
+pub struct PatchMesh {
+    vertex_array: GLuint,
+}
+
+impl PatchMesh {
+    /// Returns `None` before OpenGL 4.0, which can't tessellate.
+    pub fn new() -> Option<Self> {
+        let mut major: GLint = 0;
+        unsafe {
+            gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+        }
+        if major < 4 {
+            return None;
+        }
+
+        let mut vertex_array = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vertex_array);
+        }
+        Some(Self { vertex_array })
+    }
+
+    /// Draws `count` patches with the program and render state luminance has
+    /// bound, so it is meant to be called inside a render gate. luminance
+    /// keeps track of the bound vertex array, so its own is bound back after.
+    pub fn draw(&self, count: u32) {
+        unsafe {
+            let mut previous = 0;
+            gl::GetIntegerv(gl::VERTEX_ARRAY_BINDING, &mut previous);
+            gl::BindVertexArray(self.vertex_array);
+            gl::PatchParameteri(gl::PATCH_VERTICES, 4);
+            gl::DrawArrays(gl::PATCHES, 0, (count * 4) as GLsizei);
+            gl::BindVertexArray(previous as GLuint);
+        }
+    }
+}
+
+impl Drop for PatchMesh {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vertex_array);
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use crate::surface::OceanSurface;
+
+const WATER_DENSITY: f32 = 1000.0;
+const GRAVITY: f32 = 9.81;
+const MAX_SUBSTEP: f32 = 1.0 / 120.0;
+
+/// A rigid body floated by sampling the water surface at a set of points.
+/// Each point stands for an equal share of the body's volume and pushes up
+/// with the weight of the water it displaces, so uneven water under the hull
+/// tilts the body the way the waves do.
+#[derive(Clone, Debug)]
+pub struct RigidBody {
+    pub position: glm::Vec3,
+    pub orientation: glm::Quat,
+    pub velocity: glm::Vec3,
+    pub angular_velocity: glm::Vec3,
+    pub mass: f32,
+    /// Diagonal of the inertia tensor, in body space
+    pub inertia: glm::Vec3,
+    /// Volume of water displaced when fully submerged
+    pub volume: f32,
+    /// Sample points in body space, relative to the center of mass
+    pub points: Vec<glm::Vec3>,
+    /// Fraction of velocity relative to the water lost per second while
+    /// fully submerged
+    pub linear_drag: f32,
+    pub angular_drag: f32,
+}
+
+impl RigidBody {
+    /// A body whose volume is spread evenly over `points`, given relative to
+    /// any origin. The center of mass is put at their centroid, and the
+    /// inertia is estimated as if the mass were spread over them too.
+    pub fn from_points(
+        position: glm::Vec3,
+        points: &[glm::Vec3],
+        volume: f32,
+        mass: f32,
+    ) -> Self {
+        assert!(!points.is_empty(), "a body needs at least one point");
+        let count = points.len() as f32;
+        let centroid = points
+            .iter()
+            .fold(glm::vec3(0.0, 0.0, 0.0), |sum, point| sum + point)
+            / count;
+        let points: Vec<_> = points.iter().map(|p| p - centroid).collect();
+
+        // Keep the inertia away from zero for degenerate point sets
+        let point_mass = mass / count;
+        let minimum = mass * 0.01;
+        let mut inertia = glm::vec3(minimum, minimum, minimum);
+        for p in &points {
+            inertia.x += point_mass * (p.y * p.y + p.z * p.z);
+            inertia.y += point_mass * (p.x * p.x + p.z * p.z);
+            inertia.z += point_mass * (p.x * p.x + p.y * p.y);
+        }
+
+        Self {
+            position: position + centroid,
+            orientation: glm::quat_identity(),
+            velocity: glm::zero(),
+            angular_velocity: glm::zero(),
+            mass,
+            inertia,
+            volume,
+            points,
+            linear_drag: 0.8,
+            angular_drag: 1.5,
+        }
+    }
+
+    /// A box of the given size centered on `position`, sampled on a 3×3×3
+    /// grid.
+    pub fn cuboid(position: glm::Vec3, size: glm::Vec3, mass: f32) -> Self {
+        let mut points = Vec::with_capacity(27);
+        for &x in &[-1.0, 0.0, 1.0] {
+            for &y in &[-1.0, 0.0, 1.0] {
+                for &z in &[-1.0, 0.0, 1.0] {
+                    let corner: glm::Vec3 = glm::vec3(x, y, z) / 3.0;
+                    points.push(corner.component_mul(&size));
+                }
+            }
+        }
+        let volume = size.x * size.y * size.z;
+        Self::from_points(position, &points, volume, mass)
+    }
+
+    /// Model matrix placing the body in the world.
+    pub fn transform(&self) -> glm::Mat4 {
+        glm::translate(&glm::identity(), &self.position)
+            * glm::quat_to_mat4(&self.orientation)
+    }
+
+    /// Half the size of the box around the sample points, each padded out
+    /// by the cube of water it stands for.
+    pub fn half_extents(&self) -> glm::Vec3 {
+        let padding = (self.volume / self.points.len() as f32).cbrt() / 2.0;
+        let extents = self
+            .points
+            .iter()
+            .fold(glm::zero(), |max, point| glm::max2(&max, &glm::abs(point)));
+        extents.add_scalar(padding)
+    }
+
+    fn step(&mut self, surface: &(impl OceanSurface + ?Sized), dt: f32) {
+        let rotation = glm::quat_to_mat3(&self.orientation);
+        let point_volume = self.volume / self.points.len() as f32;
+        let point_mass = self.mass / self.points.len() as f32;
+        let point_height = point_volume.cbrt();
+
+        let mut force = glm::vec3(0.0, -GRAVITY * self.mass, 0.0);
+        let mut torque = glm::vec3(0.0, 0.0, 0.0);
+        let mut submerged = 0.0;
+
+        for point in &self.points {
+            let arm = rotation * point;
+            let world = self.position + arm;
+            let depth = surface.height_at(world.x, world.z) - world.y;
+            let fraction = (depth / point_height + 0.5).clamp(0.0, 1.0);
+            if fraction == 0.0 {
+                continue;
+            }
+            submerged += fraction;
+
+            let point_velocity =
+                self.velocity + self.angular_velocity.cross(&arm);
+            let buoyancy = WATER_DENSITY * GRAVITY * point_volume * fraction;
+            // Drag pulls the point along with the water around it
+            let relative = point_velocity - surface.velocity_at(world);
+            let drag = relative * -self.linear_drag * point_mass;
+            let point_force = glm::vec3(0.0, buoyancy, 0.0) + drag * fraction;
+
+            force += point_force;
+            torque += arm.cross(&point_force);
+        }
+        submerged /= self.points.len() as f32;
+
+        self.velocity += force / self.mass * dt;
+        self.position += self.velocity * dt;
+
+        let inverse_inertia = rotation
+            * glm::diagonal3x3(&glm::vec3(
+                1.0 / self.inertia.x,
+                1.0 / self.inertia.y,
+                1.0 / self.inertia.z,
+            ))
+            * rotation.transpose();
+        self.angular_velocity += inverse_inertia * torque * dt;
+        self.angular_velocity *= 1.0 - (self.angular_drag * submerged * dt);
+
+        let w = self.angular_velocity;
+        let spin = glm::quat(w.x, w.y, w.z, 0.0) * self.orientation;
+        self.orientation =
+            glm::quat_normalize(&(self.orientation + spin * (0.5 * dt)));
+    }
+}
+
+/// The bodies floating on the ocean.
+#[derive(Default)]
+pub struct Physics {
+    pub bodies: Vec<RigidBody>,
+}
+
+impl Physics {
+    /// Advances every body by `dt` seconds, in substeps short enough to keep
+    /// the buoyancy springs stable.
+    pub fn step(&mut self, surface: &(impl OceanSurface + ?Sized), dt: f32) {
+        let substeps = (dt / MAX_SUBSTEP).ceil().max(1.0);
+        let substep = dt / substeps;
+        for _ in 0..substeps as u32 {
+            for body in &mut self.bodies {
+                body.step(surface, substep);
+            }
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use crate::surface::OceanSurface;
+
+/// Distance between height samples while marching along a ray. Half a
+/// heightmap texel, so no wave is stepped over.
+const STEP: f32 = 0.5;
+const BISECTIONS: usize = 20;
+/// How much the box is grown above and below, so a flat sea still leaves
+/// the ray some water to march through.
+const PADDING: f32 = 0.01;
+
+#[derive(Clone, Copy, Debug)]
+pub struct RayHit {
+    pub position: glm::Vec3,
+    pub normal: glm::Vec3,
+    pub distance: f32,
+}
+
+/// Finds where a ray first crosses the surface inside the box between `min`
+/// and `max`, from above or from below. The box should enclose the drawn
+/// part of the ocean and every wave height in it.
+pub fn raycast(
+    surface: &(impl OceanSurface + ?Sized),
+    origin: glm::Vec3,
+    direction: glm::Vec3,
+    min: glm::Vec3,
+    max: glm::Vec3,
+) -> Option<RayHit> {
+    let direction = glm::normalize(&direction);
+    let padding = glm::vec3(0.0, PADDING, 0.0);
+    let (min, max) = (min - padding, max + padding);
+
+    let (mut near, mut far) = (0.0f32, f32::INFINITY);
+    for axis in 0..3 {
+        if direction[axis].abs() < 1e-6 {
+            if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                return None;
+            }
+        } else {
+            let a = (min[axis] - origin[axis]) / direction[axis];
+            let b = (max[axis] - origin[axis]) / direction[axis];
+            near = near.max(a.min(b));
+            far = far.min(a.max(b));
+        }
+    }
+    if near > far {
+        return None;
+    }
+
+    let above = |t: f32| {
+        let point = origin + direction * t;
+        point.y - surface.displacement_at(point.x, point.z).y
+    };
+
+    let mut t = near;
+    let mut previous = above(t);
+    while t < far {
+        let next_t = (t + STEP).min(far);
+        let next = above(next_t);
+        if (previous < 0.0) != (next < 0.0) {
+            let (mut low, mut high) = (t, next_t);
+            for _ in 0..BISECTIONS {
+                let middle = (low + high) / 2.0;
+                if (above(middle) < 0.0) == (previous < 0.0) {
+                    low = middle;
+                } else {
+                    high = middle;
+                }
+            }
+            let distance = (low + high) / 2.0;
+            let position = origin + direction * distance;
+            return Some(RayHit {
+                position,
+                normal: surface.normal_at(position.x, position.z),
+                distance,
+            });
+        }
+        t = next_t;
+        previous = next;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Flat(f32);
+
+    impl OceanSurface for Flat {
+        fn height_at(&self, _x: f32, _z: f32) -> f32 {
+            self.0
+        }
+    }
+
+    struct Ripples;
+
+    impl OceanSurface for Ripples {
+        fn height_at(&self, x: f32, z: f32) -> f32 {
+            (x * 0.3).sin() + (z * 0.2).cos()
+        }
+    }
+
+    fn cast(
+        surface: &impl OceanSurface,
+        origin: glm::Vec3,
+        direction: glm::Vec3,
+        (low, high): (f32, f32),
+    ) -> Option<RayHit> {
+        let min = glm::vec3(-100.0, low, -100.0);
+        let max = glm::vec3(100.0, high, 100.0);
+        raycast(surface, origin, direction, min, max)
+    }
+
+    #[test]
+    fn hits_a_flat_sea_with_a_flat_box() {
+        let origin = glm::vec3(0.0, 10.0, 0.0);
+        let direction = glm::vec3(1.0, -1.0, 0.0);
+        let hit = cast(&Flat(2.0), origin, direction, (2.0, 2.0)).unwrap();
+        assert!((hit.position - glm::vec3(8.0, 2.0, 0.0)).norm() < 1e-3);
+        assert!((hit.distance - 8.0 * 2f32.sqrt()).abs() < 1e-3);
+        assert!((hit.normal - glm::vec3(0.0, 1.0, 0.0)).norm() < 1e-6);
+    }
+
+    #[test]
+    fn hits_waves_from_above_and_below() {
+        let range = (-2.0, 2.0);
+        for &(origin, direction) in &[
+            (glm::vec3(-20.0, 5.0, 3.0), glm::vec3(1.0, -0.2, 0.1)),
+            (glm::vec3(10.0, -5.0, -7.0), glm::vec3(-0.3, 1.0, 0.4)),
+        ] {
+            let hit = cast(&Ripples, origin, direction, range).unwrap();
+            let surface = Ripples.height_at(hit.position.x, hit.position.z);
+            assert!((hit.position.y - surface).abs() < 1e-3);
+            let expected = origin + glm::normalize(&direction) * hit.distance;
+            assert!((hit.position - expected).norm() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn finds_the_first_crossing() {
+        // Skims the crests, crossing the surface many times
+        let origin = glm::vec3(-50.0, 1.5, 0.0);
+        let direction = glm::vec3(1.0, 0.0, 0.0);
+        let hit = cast(&Ripples, origin, direction, (-2.0, 2.0)).unwrap();
+        let t = (0..)
+            .map(|i| i as f32 * 0.01)
+            .find(|&t| Ripples.height_at(-50.0 + t, 0.0) > 1.5)
+            .unwrap();
+        assert!((hit.distance - t).abs() < 0.01);
+    }
+
+    #[test]
+    fn misses_outside_the_box() {
+        let up = glm::vec3(0.0, 1.0, 0.0);
+        let origin = glm::vec3(0.0, 1.0, 0.0);
+        assert!(cast(&Flat(0.0), origin, up, (0.0, 0.0)).is_none());
+        let outside = glm::vec3(200.0, 5.0, 0.0);
+        let down = glm::vec3(0.0, -1.0, 0.0);
+        assert!(cast(&Flat(0.0), outside, down, (0.0, 0.0)).is_none());
+    }
+}
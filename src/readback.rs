@@ -0,0 +1,172 @@
+use gl::types::{GLenum, GLsizei, GLsync, GLuint};
+use luminance::{
+    context::GraphicsContext,
+    framebuffer::Framebuffer,
+    pipeline::Builder,
+    pixel::{Pixel, R32F},
+    texture::{Dim2, Flat, Texture},
+};
+use std::collections::VecDeque;
+
+const SLOT_COUNT: usize = 3;
+
+/// Copies the red channel, or all four, of a texture into a ring of pixel
+/// buffer objects, so the CPU can pick the data up a frame or two later
+/// instead of stalling until the GPU has caught up.
+pub struct AsyncReadback {
+    width: u32,
+    height: u32,
+    format: GLenum,
+    channels: u32,
+    buffers: [GLuint; SLOT_COUNT],
+    next_buffer: usize,
+    pending: VecDeque<(GLuint, GLsync)>,
+    /// Target of the pipeline that binds the texture to copy
+    binding_target: Framebuffer<Flat, Dim2, R32F, ()>,
+}
+
+impl AsyncReadback {
+    pub fn new(
+        context: &mut impl GraphicsContext,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        Self::with_format(context, width, height, gl::RED, 1)
+    }
+
+    /// Reads red, green, blue and alpha, one after the other per pixel.
+    pub fn rgba(
+        context: &mut impl GraphicsContext,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        Self::with_format(context, width, height, gl::RGBA, 4)
+    }
+
+    fn with_format(
+        context: &mut impl GraphicsContext,
+        width: u32,
+        height: u32,
+        format: GLenum,
+        channels: u32,
+    ) -> Self {
+        let mut buffers = [0; SLOT_COUNT];
+        let size =
+            (width * height * channels) as usize * std::mem::size_of::<f32>();
+        unsafe {
+            gl::GenBuffers(SLOT_COUNT as GLsizei, buffers.as_mut_ptr());
+            for &buffer in &buffers {
+                gl::BindBuffer(gl::PIXEL_PACK_BUFFER, buffer);
+                gl::BufferData(
+                    gl::PIXEL_PACK_BUFFER,
+                    size as isize,
+                    std::ptr::null(),
+                    gl::STREAM_READ,
+                );
+            }
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+        }
+
+        Self {
+            width,
+            height,
+            format,
+            channels,
+            buffers,
+            next_buffer: 0,
+            pending: VecDeque::with_capacity(SLOT_COUNT),
+            binding_target: Framebuffer::new(context, [1, 1], 0)
+                .expect("framebuffer creation"),
+        }
+    }
+
+    /// Queues a copy of the base level of `texture`, which must be
+    /// `width` by `height` texels. Does nothing if every buffer is still
+    /// waiting to be polled.
+    pub fn issue<P>(
+        &mut self,
+        builder: &Builder,
+        texture: &Texture<Flat, Dim2, P>,
+    ) where
+        P: Pixel,
+    {
+        if self.pending.len() == SLOT_COUNT {
+            return;
+        }
+
+        let buffer = self.buffers[self.next_buffer];
+        self.next_buffer = (self.next_buffer + 1) % SLOT_COUNT;
+
+        let format = self.format;
+        let clear = [0.0, 0.0, 0.0, 0.0];
+        builder.pipeline(&self.binding_target, clear, |pipeline, _| {
+            // Binding the texture leaves it on the active texture unit
+            let _bound = pipeline.bind_texture(texture);
+            unsafe {
+                gl::BindBuffer(gl::PIXEL_PACK_BUFFER, buffer);
+                gl::GetTexImage(
+                    gl::TEXTURE_2D,
+                    0,
+                    format,
+                    gl::FLOAT,
+                    std::ptr::null_mut(),
+                );
+                gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+            }
+        });
+
+        unsafe {
+            let fence = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
+            self.pending.push_back((buffer, fence));
+        }
+    }
+
+    /// Returns the oldest finished copy, or `None` if the GPU hasn't gotten
+    /// to it yet. Never blocks.
+    pub fn poll(&mut self) -> Option<Vec<f32>> {
+        let &(buffer, fence) = self.pending.front()?;
+
+        unsafe {
+            let status = gl::ClientWaitSync(fence, 0, 0);
+            if status != gl::ALREADY_SIGNALED
+                && status != gl::CONDITION_SATISFIED
+            {
+                return None;
+            }
+            gl::DeleteSync(fence);
+            self.pending.pop_front();
+
+            let length = (self.width * self.height * self.channels) as usize;
+            let mut pixels = vec![0.0; length];
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, buffer);
+            let mapped = gl::MapBufferRange(
+                gl::PIXEL_PACK_BUFFER,
+                0,
+                (length * std::mem::size_of::<f32>()) as isize,
+                gl::MAP_READ_BIT,
+            ) as *const f32;
+            if !mapped.is_null() {
+                std::ptr::copy_nonoverlapping(
+                    mapped,
+                    pixels.as_mut_ptr(),
+                    length,
+                );
+            }
+            gl::UnmapBuffer(gl::PIXEL_PACK_BUFFER);
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+
+            Some(pixels)
+        }
+    }
+}
+
+impl Drop for AsyncReadback {
+    fn drop(&mut self) {
+        unsafe {
+            for (_, fence) in self.pending.drain(..) {
+                gl::DeleteSync(fence);
+            }
+            gl::DeleteBuffers(SLOT_COUNT as GLsizei, self.buffers.as_ptr());
+        }
+    }
+}
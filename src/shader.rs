@@ -1,15 +1,41 @@
 use luminance::shader::program::Program;
+use std::fmt::Display;
 
 pub fn from_strings<S, Out, Uni>(vert: &str, frag: &str) -> Program<S, Out, Uni>
 where
     S: luminance::vertex::Semantics,
     Uni: luminance::shader::program::UniformInterface,
 {
-    let (shader, warnings) = Program::from_strings(None, vert, None, frag)
-        .unwrap_or_else(|error| {
-            eprintln!("{}", error);
-            panic!();
-        });
+    unwrap_program(Program::from_strings(None, vert, None, frag))
+}
+
+pub fn from_strings_with_tessellation<S, Out, Uni>(
+    vert: &str,
+    control: &str,
+    evaluation: &str,
+    frag: &str,
+) -> Program<S, Out, Uni>
+where
+    S: luminance::vertex::Semantics,
+    Uni: luminance::shader::program::UniformInterface,
+{
+    unwrap_program(Program::from_strings(
+        Some((control, evaluation)),
+        vert,
+        None,
+        frag,
+    ))
+}
+
+fn unwrap_program<P, W, E>(result: Result<(P, Vec<W>), E>) -> P
+where
+    W: Display,
+    E: Display,
+{
+    let (shader, warnings) = result.unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        panic!();
+    });
 
     for warning in warnings {
         eprintln!("{}", warning);
@@ -0,0 +1,267 @@
+use crate::lighting::Lighting;
+use luminance::{
+    context::GraphicsContext,
+    linear::M44,
+    pipeline::{BoundTexture, Pipeline, ShadingGate},
+    pixel::{Floating, RGB32F},
+    render_state::RenderState,
+    shader::program::{Program, Uniform},
+    tess::{Mode, Tess, TessBuilder},
+    texture::{CubeFace, Cubemap, Flat, GenMipmaps, Texture},
+};
+use luminance_derive::UniformInterface;
+
+/// Side of each face of the baked sky
+const FACE_SIZE: u32 = 64;
+/// Scales the sky's luminance, in thousands of candela per square meter,
+/// down to the range the shaders light with
+const EXPOSURE: f32 = 0.05;
+
+pub type EnvironmentMap = Texture<Flat, Cubemap, RGB32F>;
+
+/// The Preetham et al. analytic daylight model, for a sun above the horizon.
+#[derive(Clone, Debug)]
+pub struct Preetham {
+    /// Unit vector pointing at the sun
+    sun_direction: glm::Vec3,
+    /// Zenith luminance and chromaticity, as Y, x and y
+    zenith: glm::Vec3,
+    /// Perez coefficients A to E for Y, x and y
+    coefficients: [[f32; 5]; 3],
+}
+
+impl Preetham {
+    /// `turbidity` runs from about 2 for a clear sky to 10 for haze. A sun
+    /// below the horizon is treated as being on it.
+    pub fn new(sun_direction: glm::Vec3, turbidity: f32) -> Self {
+        use std::f32::consts::FRAC_PI_2;
+        let t = turbidity;
+        let mut sun_direction = glm::normalize(&sun_direction);
+        sun_direction.y = sun_direction.y.max(0.001);
+        let sun_direction = glm::normalize(&sun_direction);
+        let theta = sun_direction.y.acos().min(FRAC_PI_2);
+        let (theta2, theta3) = (theta * theta, theta * theta * theta);
+
+        let chi =
+            (4.0 / 9.0 - t / 120.0) * (std::f32::consts::PI - 2.0 * theta);
+        let luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let x = t * t * (0.00166 * theta3 - 0.00375 * theta2 + 0.00209 * theta)
+            + t * (-0.02903 * theta3 + 0.06377 * theta2 - 0.03202 * theta
+                + 0.00394)
+            + (0.11693 * theta3 - 0.21196 * theta2 + 0.06052 * theta + 0.25886);
+        let y = t * t * (0.00275 * theta3 - 0.00610 * theta2 + 0.00317 * theta)
+            + t * (-0.04214 * theta3 + 0.08970 * theta2 - 0.04153 * theta
+                + 0.00516)
+            + (0.15346 * theta3 - 0.26756 * theta2 + 0.06670 * theta + 0.26688);
+
+        let coefficients = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        Self {
+            sun_direction,
+            zenith: glm::vec3(luminance, x, y),
+            coefficients,
+        }
+    }
+
+    /// Linear sRGB radiance of the sky in `direction`, in thousands of
+    /// candela per square meter. Below the horizon the sky is mirrored.
+    pub fn radiance(&self, direction: glm::Vec3) -> glm::Vec3 {
+        let mut direction = glm::normalize(&direction);
+        direction.y = direction.y.abs().max(0.001);
+        let direction = glm::normalize(&direction);
+
+        let theta = direction.y.acos();
+        let gamma = direction.dot(&self.sun_direction).clamp(-1.0, 1.0).acos();
+        let theta_sun = self.sun_direction.y.acos();
+
+        let channel = |i: usize| {
+            let coefficients = &self.coefficients[i];
+            self.zenith[i] * perez(coefficients, theta, gamma)
+                / perez(coefficients, 0.0, theta_sun)
+        };
+        let (luminance, x, y) = (channel(0), channel(1), channel(2));
+
+        // xyY to XYZ to linear sRGB
+        let xyz = glm::vec3(
+            x / y * luminance,
+            luminance,
+            (1.0 - x - y) / y * luminance,
+        );
+        glm::vec3(
+            3.2406 * xyz.x - 1.5372 * xyz.y - 0.4986 * xyz.z,
+            -0.9689 * xyz.x + 1.8758 * xyz.y + 0.0415 * xyz.z,
+            0.0557 * xyz.x - 0.2040 * xyz.y + 1.0570 * xyz.z,
+        )
+        .map(|c| c.max(0.0))
+    }
+}
+
+fn perez(coefficients: &[f32; 5], theta: f32, gamma: f32) -> f32 {
+    let [a, b, c, d, e] = *coefficients;
+    let cos_gamma = gamma.cos();
+    (1.0 + a * (b / theta.cos().max(0.01)).exp())
+        * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+}
+
+/// Unit vector through the center of texel `x`, `y` of a cubemap face with
+/// sides of `size` texels, following OpenGL's face layout.
+pub fn face_direction(face: CubeFace, x: u32, y: u32, size: u32) -> glm::Vec3 {
+    let s = 2.0 * (x as f32 + 0.5) / size as f32 - 1.0;
+    let t = 2.0 * (y as f32 + 0.5) / size as f32 - 1.0;
+    let direction = match face {
+        CubeFace::PositiveX => glm::vec3(1.0, -t, -s),
+        CubeFace::NegativeX => glm::vec3(-1.0, -t, s),
+        CubeFace::PositiveY => glm::vec3(s, 1.0, t),
+        CubeFace::NegativeY => glm::vec3(s, -1.0, -t),
+        CubeFace::PositiveZ => glm::vec3(s, -t, 1.0),
+        CubeFace::NegativeZ => glm::vec3(-s, -t, -1.0),
+    };
+    glm::normalize(&direction)
+}
+
+/// Cubemap faces in the order OpenGL numbers them
+pub const FACES: [CubeFace; 6] = [
+    CubeFace::PositiveX,
+    CubeFace::NegativeX,
+    CubeFace::PositiveY,
+    CubeFace::NegativeY,
+    CubeFace::PositiveZ,
+    CubeFace::NegativeZ,
+];
+
+/// A daylight sky for the sun of a `Lighting`, baked into a cubemap the
+/// water reflects and `Skybox` draws. Between sunset and sunrise it fades
+/// into the plain sky gradient of the `Lighting`.
+pub struct Sky {
+    pub turbidity: f32,
+    cubemap: EnvironmentMap,
+    /// Sun direction and turbidity of the last bake
+    baked: Option<(glm::Vec3, f32)>,
+}
+
+impl Sky {
+    pub fn new(context: &mut impl GraphicsContext) -> Self {
+        let cubemap = Texture::new(context, FACE_SIZE, 0, &Default::default())
+            .expect("cubemap creation");
+        Self {
+            turbidity: 3.0,
+            cubemap,
+            baked: None,
+        }
+    }
+
+    pub fn cubemap(&self) -> &EnvironmentMap {
+        &self.cubemap
+    }
+
+    /// Bakes the sky again if the sun or the turbidity has changed.
+    pub fn update(&mut self, lighting: &Lighting) {
+        let key = (lighting.sun_direction, self.turbidity);
+        if self.baked == Some(key) {
+            return;
+        }
+        self.baked = Some(key);
+
+        let model = Preetham::new(lighting.sun_direction, self.turbidity);
+        let day = ((lighting.sun_direction.y + 0.1) / 0.15).clamp(0.0, 1.0);
+        let size = FACE_SIZE as usize;
+        for &face in &FACES {
+            let mut texels = Vec::with_capacity(size * size);
+            for y in 0..FACE_SIZE {
+                for x in 0..FACE_SIZE {
+                    let direction = face_direction(face, x, y, FACE_SIZE);
+                    let night = gradient(lighting, direction);
+                    let color = glm::lerp(
+                        &night,
+                        &(model.radiance(direction) * EXPOSURE),
+                        day,
+                    );
+                    texels.push((color.x, color.y, color.z));
+                }
+            }
+            self.cubemap.upload_part(
+                GenMipmaps::No,
+                ([0, 0], face),
+                FACE_SIZE,
+                &texels,
+            );
+        }
+    }
+}
+
+/// The sky of `lighting.glsl`, for `direction`.
+fn gradient(lighting: &Lighting, direction: glm::Vec3) -> glm::Vec3 {
+    let t = direction.y.max(0.0).sqrt();
+    glm::lerp(&lighting.horizon_color, &lighting.sky_color, t)
+}
+
+#[derive(UniformInterface)]
+struct SkyboxInterface {
+    environment:
+        Uniform<&'static BoundTexture<'static, Flat, Cubemap, Floating>>,
+    inverse_view_projection: Uniform<M44>,
+}
+
+/// Draws an environment map behind everything else.
+pub struct Skybox {
+    shader: Program<(), (), SkyboxInterface>,
+    tess: Tess,
+}
+
+impl Skybox {
+    pub fn new(context: &mut impl GraphicsContext) -> Self {
+        let shader = crate::shader::from_strings(
+            include_str!("../shaders/skybox.vert"),
+            include_str!("../shaders/skybox.frag"),
+        );
+        let tess = TessBuilder::new(context)
+            .set_mode(Mode::TriangleStrip)
+            .set_vertex_nb(4)
+            .build()
+            .unwrap();
+        Self { shader, tess }
+    }
+
+    /// Fills the background with `environment`. Meant to be called first in
+    /// a pipeline, since it sits just in front of the far plane.
+    pub fn render(
+        &self,
+        context: &mut impl GraphicsContext,
+        pipeline: &Pipeline,
+        shader_gate: &ShadingGate,
+        view_projection: glm::Mat4,
+        environment: &EnvironmentMap,
+    ) {
+        let bound_environment = pipeline.bind_texture(environment);
+        let inverse = glm::inverse(&view_projection);
+        shader_gate.shade(&self.shader, |render_gate, iface| {
+            iface.environment.update(&bound_environment);
+            iface.inverse_view_projection.update(inverse.into());
+            render_gate.render(RenderState::default(), |tess_gate| {
+                tess_gate.render(context, (&self.tess).into());
+            });
+        });
+    }
+}
@@ -0,0 +1,229 @@
+use crate::breaking::BreakingDetector;
+use crate::fft::FftLayers;
+use crate::lighting::Lighting;
+use luminance::{
+    blending::{Equation, Factor},
+    context::GraphicsContext,
+    framebuffer::Framebuffer,
+    linear::M44,
+    pipeline::{BoundTexture, Builder, Pipeline, ShadingGate},
+    pixel::{Floating, RGBA32F},
+    render_state::RenderState,
+    shader::program::{Program, Uniform},
+    tess::{Mode, Tess, TessBuilder, TessSlice},
+    texture::{Dim2, Flat},
+};
+use luminance_derive::UniformInterface;
+
+/// Side of the square of texels holding the particles' state
+const SIDE: u32 = 64;
+
+#[derive(UniformInterface)]
+struct SprayUpdateInterface {
+    positions: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    velocities: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    heightmap: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    velocity: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    jacobian: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    frame: Uniform<u32>,
+    dt: Uniform<f32>,
+    center: Uniform<[f32; 2]>,
+    choppiness: Uniform<f32>,
+    jacobian_threshold: Uniform<f32>,
+    acceleration_threshold: Uniform<f32>,
+    spawn_rate: Uniform<f32>,
+    lifetime: Uniform<f32>,
+    drag: Uniform<f32>,
+    kick: Uniform<f32>,
+    size: Uniform<f32>,
+    growth: Uniform<f32>,
+}
+
+#[derive(UniformInterface)]
+struct SprayRenderInterface {
+    positions: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    velocities: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    view: Uniform<M44>,
+    projection: Uniform<M44>,
+    sun_radiance: Uniform<[f32; 3]>,
+    sky_color: Uniform<[f32; 3]>,
+    horizon_color: Uniform<[f32; 3]>,
+}
+
+/// Position and remaining life in the first slot, velocity and size in the
+/// second.
+type SprayFramebuffer = Framebuffer<Flat, Dim2, (RGBA32F, RGBA32F), ()>;
+
+/// Spray and mist thrown off breaking crests, simulated and drawn entirely on
+/// the GPU. Particles spawn where the surface folds over or accelerates down
+/// hard, by the same measures as `BreakingDetector`, leave with the velocity
+/// of the water under them, then fly under gravity and drag until they fall
+/// back in or run out of life.
+pub struct Spray {
+    /// World x and z around which particles spawn, within half a heightmap
+    /// in every direction. Usually under the camera.
+    pub center: glm::Vec2,
+    /// Chance per second that a free particle spawns, per unit of breaking
+    /// intensity
+    pub spawn_rate: f32,
+    /// Longest a particle lives, in seconds
+    pub lifetime: f32,
+    /// Fraction of its speed a particle loses per second
+    pub drag: f32,
+    /// Upward speed added at spawn per unit of breaking intensity
+    pub kick: f32,
+    /// Largest radius of a billboard at spawn, in world units
+    pub size: f32,
+    /// How fast billboards grow as the spray turns to mist, in world units
+    /// per second
+    pub growth: f32,
+    buffers: [SprayFramebuffer; 2],
+    current: usize,
+    frame: u32,
+    update_shader: Program<(), (), SprayUpdateInterface>,
+    render_shader: Program<(), (), SprayRenderInterface>,
+    tess: Tess,
+}
+
+impl Spray {
+    pub fn new(context: &mut impl GraphicsContext) -> Self {
+        let size = [SIDE, SIDE];
+        let buffers = [
+            Framebuffer::new(context, size, 0).expect("framebuffer creation"),
+            Framebuffer::new(context, size, 0).expect("framebuffer creation"),
+        ];
+        {
+            // Zero life everywhere, so every particle starts out free
+            let builder = context.pipeline_builder();
+            for buffer in &buffers {
+                builder.pipeline(buffer, [0.0, 0.0, 0.0, 0.0], |_, _| {});
+            }
+        }
+
+        let update_shader = crate::shader::from_strings(
+            include_str!("../shaders/quad.vert"),
+            include_str!("../shaders/spray-update.frag"),
+        );
+        let render_shader = crate::shader::from_strings(
+            include_str!("../shaders/spray.vert"),
+            concat!(
+                include_str!("../shaders/lighting.glsl"),
+                include_str!("../shaders/spray.frag"),
+            ),
+        );
+
+        let tess = TessBuilder::new(context)
+            .set_mode(Mode::TriangleStrip)
+            .set_vertex_nb(4)
+            .build()
+            .unwrap();
+
+        Self {
+            center: glm::zero(),
+            spawn_rate: 2.0,
+            lifetime: 3.0,
+            drag: 0.5,
+            kick: 4.0,
+            size: 0.5,
+            growth: 0.5,
+            buffers,
+            current: 0,
+            frame: 0,
+            update_shader,
+            render_shader,
+            tess,
+        }
+    }
+
+    /// Spawns and moves particles for `dt` seconds over a simulated frame,
+    /// given as its height, velocity and Jacobian maps, detecting breaking
+    /// with the thresholds of `breaking`.
+    pub fn update(
+        &mut self,
+        context: &mut impl GraphicsContext,
+        builder: &Builder,
+        dt: f32,
+        breaking: &BreakingDetector,
+        (heightmap, velocity, jacobian): &FftLayers,
+    ) {
+        if dt <= 0.0 {
+            return;
+        }
+        self.frame = self.frame.wrapping_add(1);
+
+        let (positions, velocities) = self.buffers[self.current].color_slot();
+        let output = &self.buffers[1 - self.current];
+        builder.pipeline(
+            output,
+            [0.0, 0.0, 0.0, 0.0],
+            |pipeline, shader_gate| {
+                let bound_positions = pipeline.bind_texture(positions);
+                let bound_velocities = pipeline.bind_texture(velocities);
+                let bound_heightmap = pipeline.bind_texture(heightmap);
+                let bound_velocity = pipeline.bind_texture(velocity);
+                let bound_jacobian = pipeline.bind_texture(jacobian);
+                shader_gate.shade(&self.update_shader, |render_gate, iface| {
+                    iface.positions.update(&bound_positions);
+                    iface.velocities.update(&bound_velocities);
+                    iface.heightmap.update(&bound_heightmap);
+                    iface.velocity.update(&bound_velocity);
+                    iface.jacobian.update(&bound_jacobian);
+                    iface.frame.update(self.frame);
+                    iface.dt.update(dt);
+                    iface.center.update(self.center.into());
+                    iface.choppiness.update(breaking.choppiness);
+                    iface
+                        .jacobian_threshold
+                        .update(breaking.jacobian_threshold);
+                    iface
+                        .acceleration_threshold
+                        .update(breaking.acceleration_threshold);
+                    iface.spawn_rate.update(self.spawn_rate);
+                    iface.lifetime.update(self.lifetime);
+                    iface.drag.update(self.drag);
+                    iface.kick.update(self.kick);
+                    iface.size.update(self.size);
+                    iface.growth.update(self.growth);
+                    render_gate.render(RenderState::default(), |tess_gate| {
+                        tess_gate.render(context, (&self.tess).into());
+                    });
+                });
+            },
+        );
+        self.current = 1 - self.current;
+    }
+
+    /// Draws every live particle as a soft billboard facing the camera.
+    pub fn render(
+        &self,
+        context: &mut impl GraphicsContext,
+        pipeline: &Pipeline,
+        shader_gate: &ShadingGate,
+        view: impl Into<M44>,
+        projection: impl Into<M44>,
+        lighting: &Lighting,
+    ) {
+        let (positions, velocities) = self.buffers[self.current].color_slot();
+        let bound_positions = pipeline.bind_texture(positions);
+        let bound_velocities = pipeline.bind_texture(velocities);
+        shader_gate.shade(&self.render_shader, |render_gate, iface| {
+            iface.positions.update(&bound_positions);
+            iface.velocities.update(&bound_velocities);
+            iface.view.update(view.into());
+            iface.projection.update(projection.into());
+            iface.sun_radiance.update(lighting.sun_radiance().into());
+            iface.sky_color.update(lighting.sky_color.into());
+            iface.horizon_color.update(lighting.horizon_color.into());
+            let state = RenderState::default().set_blending((
+                Equation::Additive,
+                Factor::SrcAlpha,
+                Factor::One,
+            ));
+            render_gate.render(state, |tess_gate| {
+                let count = (SIDE * SIDE) as usize;
+                let slice = TessSlice::inst_whole(&self.tess, count);
+                tess_gate.render(context, slice);
+            });
+        });
+    }
+}
@@ -0,0 +1,48 @@
+/// Queries every ocean backend can answer, so gameplay code doesn't need to
+/// know whether the waves are simulated on the GPU or the CPU.
+pub trait OceanSurface {
+    /// Surface height in world units at world coordinates `x` and `z`, for
+    /// the last simulated frame.
+    fn height_at(&self, x: f32, z: f32) -> f32;
+
+    /// How far the surface point above (`x`, 0, `z`) has moved from rest, in
+    /// world units. Like `surface_at` in `ocean-surface.glsl`, the surface
+    /// only moves vertically, so this is `(0, height_at(x, z), 0)`.
+    fn displacement_at(&self, x: f32, z: f32) -> glm::Vec3 {
+        glm::vec3(0.0, self.height_at(x, z), 0.0)
+    }
+
+    /// Velocity of the water at the surface above (`x`, 0, `z`), in world
+    /// units per second. Backends that don't track it report still water.
+    fn surface_velocity_at(&self, _x: f32, _z: f32) -> glm::Vec3 {
+        glm::zero()
+    }
+
+    /// Wavenumber of the waves that dominate the motion of the water, in
+    /// radians per world unit.
+    fn peak_wavenumber(&self) -> f32 {
+        0.0
+    }
+
+    /// Velocity of the water at `position`. Below the surface the orbital
+    /// motion dies off exponentially with depth, at the rate of the peak
+    /// wavenumber. Above the surface there is no water to move.
+    fn velocity_at(&self, position: glm::Vec3) -> glm::Vec3 {
+        let depth = self.height_at(position.x, position.z) - position.y;
+        if depth < 0.0 {
+            return glm::zero();
+        }
+        let attenuation = (-self.peak_wavenumber() * depth).exp();
+        self.surface_velocity_at(position.x, position.z) * attenuation
+    }
+
+    /// Upward facing surface normal, from central differences one world
+    /// unit apart like the ones `ocean-surface.glsl` takes.
+    fn normal_at(&self, x: f32, z: f32) -> glm::Vec3 {
+        let left = self.height_at(x - 1.0, z);
+        let right = self.height_at(x + 1.0, z);
+        let up = self.height_at(x, z - 1.0);
+        let down = self.height_at(x, z + 1.0);
+        glm::normalize(&glm::vec3(left - right, 2.0, up - down))
+    }
+}
@@ -0,0 +1,157 @@
+use crate::ocean::{FrameView, Ocean};
+use crate::surface::OceanSurface;
+use luminance::{
+    blending::{Equation, Factor},
+    context::GraphicsContext,
+    linear::M44,
+    pipeline::{BoundTexture, Pipeline, ShadingGate},
+    pixel::Floating,
+    render_state::RenderState,
+    shader::program::{Program, Uniform},
+    tess::{Mode, Tess, TessBuilder},
+    texture::{Dim2, Flat},
+};
+use luminance_derive::UniformInterface;
+
+#[derive(UniformInterface)]
+struct UnderwaterInterface {
+    inverse_view_projection: Uniform<M44>,
+    depth: Uniform<f32>,
+    waterline: Uniform<i32>,
+    heightmap: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    wake: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    wake_origin: Uniform<[f32; 2]>,
+    wake_size: Uniform<f32>,
+    kelvin: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    kelvin_origin: Uniform<[f32; 2]>,
+    kelvin_size: Uniform<f32>,
+    bathymetry: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    bathymetry_origin: Uniform<[f32; 2]>,
+    bathymetry_size: Uniform<f32>,
+    peak_wavenumber: Uniform<f32>,
+    deep_color: Uniform<[f32; 3]>,
+    absorption: Uniform<[f32; 3]>,
+    refractive_index: Uniform<f32>,
+    caustics_map: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    caustics_depth: Uniform<f32>,
+    sun_direction: Uniform<[f32; 3]>,
+    sun_radiance: Uniform<[f32; 3]>,
+    sky_color: Uniform<[f32; 3]>,
+    horizon_color: Uniform<[f32; 3]>,
+}
+
+/// Draws what an eye under the surface sees besides the surface: water that
+/// absorbs light with distance, shafts of sunlight, and the seabed lit by
+/// caustics. Also draws the waterline where the surface crosses the lens.
+pub struct Underwater {
+    shader: Program<(), (), UnderwaterInterface>,
+    tess: Tess,
+}
+
+impl Underwater {
+    pub fn new(context: &mut impl GraphicsContext) -> Self {
+        let shader = crate::shader::from_strings(
+            include_str!("../shaders/underwater.vert"),
+            concat!(
+                include_str!("../shaders/lighting.glsl"),
+                include_str!("../shaders/underwater.glsl"),
+                include_str!("../shaders/ocean-surface.glsl"),
+                include_str!("../shaders/underwater.frag"),
+            ),
+        );
+        let tess = TessBuilder::new(context)
+            .set_mode(Mode::TriangleStrip)
+            .set_vertex_nb(4)
+            .build()
+            .unwrap();
+        Self { shader, tess }
+    }
+
+    /// Fills the pixels whose view starts below the surface, just in front
+    /// of a skybox and behind everything else.
+    pub fn render_volume(
+        &self,
+        context: &mut impl GraphicsContext,
+        pipeline: &Pipeline,
+        shader_gate: &ShadingGate,
+        ocean: &Ocean,
+        view: &FrameView,
+    ) {
+        self.render(context, pipeline, shader_gate, ocean, view, false);
+    }
+
+    /// Draws the waterline over everything, with the part of the view that
+    /// has dipped below the surface veiled in water.
+    pub fn render_waterline(
+        &self,
+        context: &mut impl GraphicsContext,
+        pipeline: &Pipeline,
+        shader_gate: &ShadingGate,
+        ocean: &Ocean,
+        view: &FrameView,
+    ) {
+        self.render(context, pipeline, shader_gate, ocean, view, true);
+    }
+
+    fn render(
+        &self,
+        context: &mut impl GraphicsContext,
+        pipeline: &Pipeline,
+        shader_gate: &ShadingGate,
+        ocean: &Ocean,
+        view: &FrameView,
+        waterline: bool,
+    ) {
+        let lighting = view.lighting;
+        let bathymetry = ocean.bathymetry();
+        let material = &ocean.material;
+        let (wake, kelvin) = (&ocean.wake, &ocean.kelvin);
+        let heightmap = pipeline.bind_texture(ocean.heightmap());
+        let wake_texture = pipeline.bind_texture(wake.texture());
+        let kelvin_texture = pipeline.bind_texture(kelvin.texture());
+        let seabed = pipeline.bind_texture(bathymetry.texture());
+        let caustics = pipeline.bind_texture(ocean.caustics.texture());
+        let inverse = glm::inverse(&view.view_projection);
+
+        let (depth, render_state) = if waterline {
+            let blending = (
+                Equation::Additive,
+                Factor::SrcAlpha,
+                Factor::SrcAlphaComplement,
+            );
+            (-0.99999, RenderState::default().set_blending(blending))
+        } else {
+            // Just in front of the skybox
+            (0.99998, RenderState::default())
+        };
+
+        shader_gate.shade(&self.shader, |render_gate, iface| {
+            iface.inverse_view_projection.update(inverse.into());
+            iface.depth.update(depth);
+            iface.waterline.update(waterline as i32);
+            iface.heightmap.update(&heightmap);
+            iface.wake.update(&wake_texture);
+            iface.wake_origin.update(wake.origin.into());
+            iface.wake_size.update(wake.size);
+            iface.kelvin.update(&kelvin_texture);
+            iface.kelvin_origin.update(kelvin.origin.into());
+            iface.kelvin_size.update(kelvin.size);
+            iface.bathymetry.update(&seabed);
+            iface.bathymetry_origin.update(bathymetry.origin().into());
+            iface.bathymetry_size.update(bathymetry.size());
+            iface.peak_wavenumber.update(ocean.peak_wavenumber());
+            iface.deep_color.update(material.deep_color.into());
+            iface.absorption.update(material.absorption.into());
+            iface.refractive_index.update(material.refractive_index);
+            iface.caustics_map.update(&caustics);
+            iface.caustics_depth.update(ocean.caustics.depth);
+            iface.sun_direction.update(lighting.sun_direction.into());
+            iface.sun_radiance.update(lighting.sun_radiance().into());
+            iface.sky_color.update(lighting.sky_color.into());
+            iface.horizon_color.update(lighting.horizon_color.into());
+            render_gate.render(render_state, |tess_gate| {
+                tess_gate.render(context, (&self.tess).into());
+            });
+        });
+    }
+}
@@ -0,0 +1,264 @@
+use crate::fft::{Fft, FftFramebuffer, FftTexture, N};
+use luminance::{
+    context::GraphicsContext,
+    framebuffer::Framebuffer,
+    pipeline::{BoundTexture, Builder},
+    pixel::{Floating, R32F, RGBA32F},
+    render_state::RenderState,
+    shader::program::{Program, Uniform},
+    tess::{Mode, Tess, TessBuilder},
+    texture::{Dim2, Flat, GenMipmaps, Texture},
+};
+use luminance_derive::UniformInterface;
+
+/// The FFT size, so the ripples can be blurred by convolution
+const RESOLUTION: u32 = N;
+/// Standard deviation of the blur over the drawn ripples, in texels
+const BLUR: f32 = 0.75;
+
+#[derive(UniformInterface)]
+struct WakeInterface {
+    previous: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    source: Uniform<&'static BoundTexture<'static, Flat, Dim2, Floating>>,
+    source_strength: Uniform<f32>,
+    courant: Uniform<f32>,
+    damping: Uniform<f32>,
+    advection: Uniform<[f32; 2]>,
+    step_ratio: Uniform<f32>,
+}
+
+pub type WakeTexture = Texture<Flat, Dim2, RGBA32F>;
+type WakeFramebuffer = Framebuffer<Flat, Dim2, RGBA32F, ()>;
+
+/// A push on the water, spread over a disc with soft edges.
+#[derive(Clone, Copy, Debug)]
+pub struct Disturbance {
+    /// Center, in world x and z
+    pub position: glm::Vec2,
+    pub radius: f32,
+    /// Height added at the center. Negative values push the water down.
+    pub strength: f32,
+}
+
+/// Extra waves on top of the FFT ocean, from a 2D wave equation over a square
+/// area of the world. Objects disturb it every frame, and the ripples spread,
+/// drift with `flow` and die down over time.
+pub struct Wake {
+    /// World x and z of the area's corner with the lowest coordinates
+    pub origin: glm::Vec2,
+    /// Side of the area, in world units
+    pub size: f32,
+    /// Speed the ripples spread at, in world units per second
+    pub wave_speed: f32,
+    /// Fraction of the ripples' height lost per second
+    pub damping: f32,
+    /// Current the ripples drift with, in world units per second
+    pub flow: glm::Vec2,
+    buffers: [WakeFramebuffer; 2],
+    current: usize,
+    /// Length of the last substep, in seconds
+    last_dt: Option<f32>,
+    source: Texture<Flat, Dim2, R32F>,
+    /// Gaussian kernel of `BLUR`, centered on texel (0, 0)
+    blur_kernel: FftTexture,
+    blurred: FftFramebuffer,
+    disturbances: Vec<Disturbance>,
+    shader: Program<(), (), WakeInterface>,
+    tess: Tess,
+}
+
+impl Wake {
+    pub fn new(context: &mut impl GraphicsContext) -> Self {
+        let size = [RESOLUTION, RESOLUTION];
+        let buffers = [
+            Framebuffer::new(context, size, 0).expect("framebuffer creation"),
+            Framebuffer::new(context, size, 0).expect("framebuffer creation"),
+        ];
+        let blurred =
+            Framebuffer::new(context, size, 0).expect("framebuffer creation");
+        {
+            // Start from calm water
+            let builder = context.pipeline_builder();
+            for buffer in buffers.iter().chain(Some(&blurred)) {
+                builder.pipeline(buffer, [0.0, 0.0, 0.0, 0.0], |_, _| {});
+            }
+        }
+
+        use luminance::texture::{MagFilter, MinFilter, Sampler};
+        let mut sampler = Sampler::default();
+        sampler.mag_filter = MagFilter::Nearest;
+        sampler.min_filter = MinFilter::Nearest;
+        let source = Texture::new(context, size, 0, &sampler).unwrap();
+
+        let blur_kernel =
+            Texture::new(context, size, 0, &Default::default()).unwrap();
+        blur_kernel.upload(GenMipmaps::No, &blur_kernel_texels());
+
+        let shader = crate::shader::from_strings(
+            include_str!("../shaders/quad.vert"),
+            include_str!("../shaders/wake.frag"),
+        );
+
+        let tess = TessBuilder::new(context)
+            .set_mode(Mode::TriangleStrip)
+            .set_vertex_nb(4)
+            .build()
+            .unwrap();
+
+        Self {
+            origin: glm::vec2(-128.0, -128.0),
+            size: 256.0,
+            wave_speed: 8.0,
+            damping: 0.4,
+            flow: glm::zero(),
+            buffers,
+            current: 0,
+            last_dt: None,
+            source,
+            blur_kernel,
+            blurred,
+            disturbances: Vec::new(),
+            shader,
+            tess,
+        }
+    }
+
+    /// Queues a disturbance for the next step.
+    pub fn disturb(&mut self, disturbance: Disturbance) {
+        self.disturbances.push(disturbance);
+    }
+
+    /// Ripple heights in the red channel, covering the area from `origin`
+    /// to `origin + size`. Slightly blurred, so the texel-sized spikes the
+    /// sources leave on the grid don't show on the surface.
+    pub fn texture(&self) -> &WakeTexture {
+        self.blurred.color_slot()
+    }
+
+    /// Applies the queued disturbances and advances the ripples by `dt`
+    /// seconds, in substeps short enough to keep the simulation stable, then
+    /// blurs them with `fft` for drawing. Does nothing while time stands
+    /// still.
+    pub fn step(
+        &mut self,
+        context: &mut impl GraphicsContext,
+        builder: &Builder,
+        dt: f32,
+        fft: &Fft,
+    ) {
+        if dt <= 0.0 {
+            // Nothing moved, so nothing pushed the water either
+            self.disturbances.clear();
+            return;
+        }
+
+        let texel_size = self.size / RESOLUTION as f32;
+        let max_dt = texel_size / (self.wave_speed * 2f32.sqrt());
+        let substeps = (dt / max_dt).ceil();
+        let dt = dt / substeps;
+        // The state holds the height one substep back rather than a
+        // velocity, so the change since then is scaled to the new length
+        let step_ratio = self.last_dt.map_or(1.0, |last_dt| dt / last_dt);
+        self.last_dt = Some(dt);
+
+        let has_source = !self.disturbances.is_empty();
+        if has_source {
+            self.source.upload(GenMipmaps::No, &self.rasterize());
+            self.disturbances.clear();
+        }
+
+        let courant = (self.wave_speed * dt / texel_size).powi(2);
+        let damping = (-self.damping * dt).exp();
+        let advection = self.flow * dt / self.size;
+
+        for substep in 0..substeps as u32 {
+            let input = self.buffers[self.current].color_slot();
+            let output = &self.buffers[1 - self.current];
+            let Self {
+                source,
+                shader,
+                tess,
+                ..
+            } = &*self;
+            builder.pipeline(
+                output,
+                [0.0, 0.0, 0.0, 0.0],
+                |pipeline, shader_gate| {
+                    let bound_previous = pipeline.bind_texture(input);
+                    let bound_source = pipeline.bind_texture(source);
+                    shader_gate.shade(shader, |render_gate, iface| {
+                        iface.previous.update(&bound_previous);
+                        iface.source.update(&bound_source);
+                        let first = has_source && substep == 0;
+                        iface.source_strength.update(first as i32 as f32);
+                        iface.courant.update(courant);
+                        iface.damping.update(damping);
+                        iface.advection.update(advection.into());
+                        let ratio = if substep == 0 { step_ratio } else { 1.0 };
+                        iface.step_ratio.update(ratio);
+                        render_gate.render(
+                            RenderState::default(),
+                            |tess_gate| {
+                                tess_gate.render(context, tess.into());
+                            },
+                        );
+                    });
+                },
+            );
+            self.current = 1 - self.current;
+        }
+
+        fft.convolve(
+            context,
+            builder,
+            self.buffers[self.current].color_slot(),
+            &self.blur_kernel,
+            &mut self.blurred,
+        );
+    }
+
+    /// Draws the queued disturbances into a source map for the shader.
+    fn rasterize(&self) -> Vec<f32> {
+        let resolution = RESOLUTION as usize;
+        let texel_size = self.size / RESOLUTION as f32;
+        let mut pixels = vec![0.0; resolution * resolution];
+        for disturbance in &self.disturbances {
+            let center = (disturbance.position - self.origin) / texel_size;
+            let radius = disturbance.radius / texel_size;
+            let low = |c: f32| (c - radius).floor().max(0.0) as usize;
+            let high = |c: f32| {
+                ((c + radius).ceil().max(0.0) as usize).min(resolution)
+            };
+            for y in low(center.y)..high(center.y) {
+                for x in low(center.x)..high(center.x) {
+                    let offset =
+                        glm::vec2(x as f32 + 0.5, y as f32 + 0.5) - center;
+                    let distance = glm::length(&offset) / radius;
+                    if distance < 1.0 {
+                        let falloff = (distance * std::f32::consts::PI).cos();
+                        pixels[y * resolution + x] +=
+                            disturbance.strength * (falloff + 1.0) / 2.0;
+                    }
+                }
+            }
+        }
+        pixels
+    }
+}
+
+/// Texels of a normalized Gaussian of `BLUR`, wrapped around the edges so
+/// that its center sits on texel (0, 0).
+fn blur_kernel_texels() -> Vec<(f32, f32, f32, f32)> {
+    let resolution = RESOLUTION as i32;
+    let half = resolution / 2;
+    let offset = |i: i32| (i + half) % resolution - half;
+    let weights: Vec<f32> = (0..resolution * resolution)
+        .map(|i| {
+            let x = offset(i % resolution) as f32;
+            let y = offset(i / resolution) as f32;
+            (-(x * x + y * y) / (2.0 * BLUR * BLUR)).exp()
+        })
+        .collect();
+    let total: f32 = weights.iter().sum();
+    weights.iter().map(|w| (w / total, 0.0, 0.0, 0.0)).collect()
+}